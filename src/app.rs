@@ -40,11 +40,22 @@ use crate::{
     config::Config,
     fs::FsExplorer,
     handler::handle_keyboard_event,
-    media::player::{MusicPlayer, Player},
+    media::{
+        device::OutputDevice,
+        media::{Media, Source},
+        output::{self, null_output, Output, OutputKind},
+        player::{MusicPlayer, Player},
+        stream::is_url,
+    },
+    sleep::SleepTimer,
     ui::{
+        device::{draw_device_picker, DevicePicker},
+        equalizer::{draw_equalizer, EqView},
         fs::draw_fs_tree,
+        history::{draw_history, HistoryView},
         music_board::draw_music_board,
         music_board::MusicController,
+        station_log::{draw_station_log, StationLogView},
         EventType,
     },
 };
@@ -52,32 +63,103 @@ use crate::{
 pub enum InputMode {
     Normal,
 }
+
+// pane the arrow keys go to
+#[derive(PartialEq, Eq)]
+pub enum Focus {
+    Explorer,
+    Playlist,
+}
+
 pub struct App {
     pub mode: InputMode,
+    pub focus: Focus,
     pub fs: FsExplorer,
     pub player: MusicPlayer,
     pub music_controller: MusicController,
+    pub device_picker: Option<DevicePicker>,
+    pub history_view: Option<HistoryView>,
+    pub station_log: Option<StationLogView>,
+    pub eq_view: Option<EqView>,
+    pub sleep: SleepTimer,
     pub config: Config,
     msg: String,
 }
 
 impl App {
     pub fn new() -> Option<Self> {
+        let config = Config::default();
+        let (output, device, mut msg) = Self::open_output(&config);
+        let mut player = MusicPlayer::with_output(output);
+        player.device = device;
+        player.replay_gain = config.replay_gain();
+        player.equalizer = config.equalizer();
+        player.segmentation = config.segmentation();
+        player.ramp = config.ramp();
+        for arg in config.media_args() {
+            let src = match is_url(arg.as_str()) {
+                true => Source::Http(arg),
+                false => Source::Local(arg),
+            };
+            if let Err(err) = player.add_to_list(Media { src }, false) {
+                msg = err.to_string();
+            }
+        }
         Some(Self {
             mode: InputMode::Normal,
+            focus: Focus::Explorer,
             fs: FsExplorer::default(Some(|err| {
                 eprintln!("{}", err);
             }))
             .ok()?,
-            player: Player::new(),
+            player,
             music_controller: MusicController {
                 state: ListState::default(),
             },
-            msg: "Welcome to RustPlayer".to_string(),
-            config: Config::default(),
+            device_picker: None,
+            history_view: None,
+            station_log: None,
+            eq_view: None,
+            sleep: SleepTimer::Off,
+            msg,
+            config,
         })
     }
 
+    // output from the command line or config, falling back to the null output
+    fn open_output(config: &Config) -> (Box<dyn Output>, Option<OutputDevice>, String) {
+        let kind = match config.output_kind() {
+            Ok(kind) => kind,
+            Err(err) => {
+                return (
+                    Box::new(null_output(1.0)),
+                    None,
+                    format!("{}, playing to null output", err),
+                )
+            }
+        };
+        // reopen the device picked last time
+        let device = config.settings.saved_device();
+        if let Ok(output) = output::open(&kind, device.as_ref()) {
+            let msg = match kind {
+                OutputKind::Device => "Welcome to RustPlayer".to_string(),
+                _ => format!("Welcome to RustPlayer, playing to {}", output.name()),
+            };
+            return (output, device, msg);
+        }
+        if let (OutputKind::Device, Some(device)) = (&kind, &device) {
+            if let Ok(output) = output::open(&kind, None) {
+                let msg = format!("Output device not found: {}", device.name);
+                return (output, None, msg);
+            }
+        }
+        (
+            Box::new(null_output(1.0)),
+            None,
+            "No audio output available, playing to null output".to_string(),
+        )
+    }
+
     // block thread and show screen
     pub fn run(&mut self) -> Result<(), Error> {
         let mut stdout = stdout();
@@ -89,7 +171,7 @@ impl App {
         self.draw_frame(&mut terminal)?;
         // tick daemon thread
         let (sd, rd) = mpsc::channel::<EventType>();
-        let tick = self.config.tick_gap.clone();
+        let tick = self.config.tick_gap;
         thread::spawn(move || loop {
             thread::sleep(tick);
             let _ = sd.send(EventType::Player);
@@ -130,12 +212,11 @@ impl App {
         });
         loop {
             thread::sleep(self.config.refresh_rate);
-            if let Ok(_) = exit_receiver.try_recv() {
+            if exit_receiver.try_recv().is_ok() {
                 break;
             }
-            match evt_receiver.try_recv() {
-                Ok(code) => handle_keyboard_event(self, code),
-                _ => {}
+            if let Ok(code) = evt_receiver.try_recv() {
+                handle_keyboard_event(self, code);
             }
             // 10 fps
             self.draw_frame(&mut terminal)?;
@@ -143,6 +224,8 @@ impl App {
                 self.handle_events(event);
             }
         }
+        // do not cut the sound off mid-sample
+        self.player.shutdown();
         disable_raw_mode()?;
         execute!(terminal.backend_mut(), LeaveAlternateScreen,)?;
         terminal.show_cursor()?;
@@ -155,7 +238,10 @@ impl App {
         match event {
             EventType::Player => {
                 let player = &mut self.player;
-                player.tick();
+                if let Err(err) = player.tick() {
+                    self.set_msg(&err.to_string());
+                }
+                self.sleep.tick(&mut self.player);
             }
         }
     }
@@ -173,6 +259,10 @@ impl App {
                 .split(size);
             self.draw_header(frame, chunks[0]);
             self.draw_body(frame, chunks[1]).unwrap();
+            draw_device_picker(self, frame, size);
+            draw_history(self, frame, size);
+            draw_station_log(self, frame, size);
+            draw_equalizer(self, frame, size);
         })?;
         Ok(())
     }
@@ -181,8 +271,12 @@ impl App {
     where
         B: Backend,
     {
+        let mut title = String::from("RustPlayer - Music Player For Rust");
+        if let Some(countdown) = self.sleep.countdown() {
+            title = format!("{} ({})", title, countdown);
+        }
         let block = Block::default()
-            .title("RustPlayer - Music Player For Rust")
+            .title(title)
             .borders(Borders::ALL)
             .title_alignment(Alignment::Left)
             .border_type(BorderType::Rounded)
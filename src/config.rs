@@ -15,11 +15,26 @@
 // You should have received a copy of the GNU General Public License
 // along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::time::Duration;
+use std::{collections::BTreeMap, env, fs, path::PathBuf, time::Duration};
+
+use failure::Error;
+use serde::{Deserialize, Serialize};
+
+use crate::media::{
+    device::OutputDevice,
+    equalizer::{EqControl, BANDS, PRESETS},
+    loudness::{GainMode, ReplayGain},
+    output::{OutputError, OutputKind},
+    segment::SegmentSettings,
+};
+
+// upper bound of `fade_ms`
+const MAX_FADE_MS: u64 = 1000;
 
 pub struct Config {
     pub refresh_rate: Duration,
     pub tick_gap: Duration,
+    pub settings: Settings,
 }
 
 impl Config {
@@ -27,6 +42,166 @@ impl Config {
         Self {
             refresh_rate: Duration::from_millis(15),
             tick_gap: Duration::from_millis(100),
+            settings: Settings::load(),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        self.settings.save()
+    }
+
+    // `--output <kind>` on the command line wins over the config file
+    pub fn output_kind(&self) -> Result<OutputKind, OutputError> {
+        let mut args = env::args().skip(1);
+        let mut arg = None;
+        while let Some(a) = args.next() {
+            if a == "--output" {
+                arg = args.next();
+            } else if let Some(kind) = a.strip_prefix("--output=") {
+                arg = Some(kind.to_string());
+            }
+        }
+        match arg.or_else(|| self.settings.output.clone()) {
+            Some(kind) => kind.parse(),
+            None => Ok(OutputKind::Device),
+        }
+    }
+
+    // files and station urls on the command line, queued at start
+    pub fn media_args(&self) -> Vec<String> {
+        let mut args = env::args().skip(1);
+        let mut media = vec![];
+        while let Some(a) = args.next() {
+            if a == "--output" {
+                args.next();
+            } else if !a.starts_with("--") {
+                media.push(a);
+            }
+        }
+        media
+    }
+
+    // unknown modes fall back to track gain
+    pub fn replay_gain(&self) -> ReplayGain {
+        let settings = &self.settings;
+        let default = ReplayGain::default();
+        ReplayGain {
+            mode: match settings.replay_gain.as_deref() {
+                Some("off") => GainMode::Off,
+                Some("album") => GainMode::Album,
+                _ => GainMode::Track,
+            },
+            preamp: settings.preamp.unwrap_or(default.preamp),
+            clip_protection: settings.clip_protection.unwrap_or(default.clip_protection),
+        }
+    }
+
+    pub fn ramp(&self) -> Duration {
+        // longer would hold up quitting
+        Duration::from_millis(self.settings.fade_ms.unwrap_or(40).min(MAX_FADE_MS))
+    }
+
+    pub fn segmentation(&self) -> SegmentSettings {
+        let settings = &self.settings;
+        let default = SegmentSettings::default();
+        SegmentSettings {
+            threshold: settings.silence_threshold.unwrap_or(default.threshold),
+            min_silence: settings.min_silence.unwrap_or(default.min_silence),
+            min_segment: settings.min_segment.unwrap_or(default.min_segment),
+        }
+    }
+
+    // saved bands, missing ones are flat
+    pub fn equalizer(&self) -> EqControl {
+        let settings = &self.settings;
+        let mut gains = [0.0; BANDS];
+        if let Some(bands) = &settings.eq_bands {
+            for (gain, band) in gains.iter_mut().zip(bands) {
+                *gain = *band;
+            }
+        }
+        let eq = EqControl::new([0.0; BANDS], settings.eq_enabled.unwrap_or(true));
+        eq.set_gains(gains);
+        eq
+    }
+
+    // built-in presets followed by the user's own
+    pub fn eq_presets(&self) -> Vec<(String, [f32; BANDS])> {
+        let mut presets: Vec<(String, [f32; BANDS])> = PRESETS
+            .iter()
+            .map(|(name, gains)| (name.to_string(), *gains))
+            .collect();
+        for (name, bands) in &self.settings.eq_presets {
+            let mut gains = [0.0; BANDS];
+            for (gain, band) in gains.iter_mut().zip(bands) {
+                *gain = *band;
+            }
+            presets.push((name.clone(), gains));
+        }
+        presets
+    }
+}
+
+/// Settings kept across runs in `<config dir>/rustplayer/config.toml`.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct Settings {
+    // device, null, null:<speed factor> or wav:<path>
+    pub output: Option<String>,
+    // output device picked in the device list
+    pub device_host: Option<String>,
+    pub device: Option<String>,
+    // off, track or album
+    pub replay_gain: Option<String>,
+    // dB on top of the replay gain
+    pub preamp: Option<f32>,
+    pub clip_protection: Option<bool>,
+    // volume ramp on pause, resume, stop, skip and quit in milliseconds
+    pub fade_ms: Option<u64>,
+    // sentence splitting: dBFS counted as silence, seconds of silence
+    // between sentences and shortest sentence in seconds
+    pub silence_threshold: Option<f32>,
+    pub min_silence: Option<f32>,
+    pub min_segment: Option<f32>,
+    pub eq_enabled: Option<bool>,
+    // dB from 31 Hz up to 16 kHz
+    pub eq_bands: Option<Vec<f32>>,
+    // None once the bands have been edited by hand
+    pub eq_preset: Option<String>,
+    // name -> bands, saved from the equalizer popup
+    pub eq_presets: BTreeMap<String, Vec<f32>>,
+}
+
+impl Settings {
+    fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("rustplayer").join("config.toml"))
+    }
+
+    pub fn saved_device(&self) -> Option<OutputDevice> {
+        match (&self.device_host, &self.device) {
+            (Some(host), Some(name)) => Some(OutputDevice {
+                host: host.clone(),
+                name: name.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    // a missing or broken file gives the defaults
+    pub fn load() -> Self {
+        Self::path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|s| toml::from_str(s.as_str()).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        if let Some(path) = Self::path() {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(path, toml::to_string(self)?)?;
         }
+        Ok(())
     }
 }
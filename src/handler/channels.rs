@@ -0,0 +1,38 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use crossterm::event::KeyCode;
+
+use crate::app::App;
+
+// balance change per key press
+const BALANCE_STEP: f32 = 0.1;
+
+pub fn handle_channels(app: &mut App, code: KeyCode) -> bool {
+    let channels = &app.player.channels;
+    match code {
+        KeyCode::Char('(') => channels.set_balance(channels.balance() - BALANCE_STEP),
+        KeyCode::Char(')') => channels.set_balance(channels.balance() + BALANCE_STEP),
+        KeyCode::Char('m') | KeyCode::Char('M') => channels.toggle_mono(),
+        KeyCode::Char('w') | KeyCode::Char('W') => channels.toggle_swap(),
+        KeyCode::Char('o') | KeyCode::Char('O') => channels.toggle_crossfeed(),
+        _ => {
+            return false;
+        }
+    }
+    true
+}
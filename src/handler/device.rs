@@ -0,0 +1,91 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use crossterm::event::KeyCode;
+use tui::widgets::ListState;
+
+use crate::{
+    app::App,
+    media::{device::output_devices, player::Player},
+    ui::device::DevicePicker,
+};
+
+fn open_picker(app: &mut App) {
+    let mut devices = vec![None];
+    devices.extend(output_devices().into_iter().map(Some));
+    let current = devices
+        .iter()
+        .position(|device| *device == app.player.device)
+        .unwrap_or(0);
+    let mut state = ListState::default();
+    state.select(Some(current));
+    app.device_picker = Some(DevicePicker { devices, state });
+}
+
+fn select_device(app: &mut App) {
+    let picker = match app.device_picker.take() {
+        Some(picker) => picker,
+        None => return,
+    };
+    let device = match picker.state.selected() {
+        Some(selected) => picker.devices[selected].clone(),
+        None => return,
+    };
+    if let Err(err) = app.player.set_device(device.clone()) {
+        app.set_msg(&err.to_string());
+        return;
+    }
+    let settings = &mut app.config.settings;
+    settings.device_host = device.as_ref().map(|device| device.host.clone());
+    settings.device = device.as_ref().map(|device| device.name.clone());
+    match app.config.save() {
+        Ok(_) => app.set_msg("Output device changed"),
+        Err(_) => app.set_msg("Output device changed, but saving config failed"),
+    }
+}
+
+// the picker is modal, it eats every key while open
+pub fn handle_device(app: &mut App, code: KeyCode) -> bool {
+    let picker = match &mut app.device_picker {
+        Some(picker) => picker,
+        None => {
+            if let KeyCode::Char('d') | KeyCode::Char('D') = code {
+                open_picker(app);
+                return true;
+            }
+            return false;
+        }
+    };
+    let len = picker.devices.len();
+    let selected = picker.state.selected().unwrap_or(0);
+    match code {
+        KeyCode::Down => {
+            picker.state.select(Some((selected + 1) % len));
+        }
+        KeyCode::Up => {
+            picker.state.select(Some((selected + len - 1) % len));
+        }
+        KeyCode::Enter => {
+            select_device(app);
+        }
+        KeyCode::Esc | KeyCode::Char('d') | KeyCode::Char('D') => {
+            app.device_picker = None;
+        }
+        _ => {}
+    }
+    true
+}
@@ -0,0 +1,125 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use crossterm::event::KeyCode;
+
+use crate::{app::App, media::equalizer::BANDS, ui::equalizer::EqView};
+
+// dB per key press
+const STEP: f32 = 1.0;
+
+// the bands changed by hand, they no longer match a preset
+fn edited(app: &mut App) {
+    app.config.settings.eq_preset = None;
+}
+
+// move to the next (or previous) preset
+fn cycle_preset(app: &mut App, forward: bool) {
+    let presets = app.config.eq_presets();
+    let current = presets
+        .iter()
+        .position(|(name, _)| Some(name) == app.config.settings.eq_preset.as_ref());
+    let len = presets.len();
+    let next = match (current, forward) {
+        (Some(i), true) => (i + 1) % len,
+        (Some(i), false) => (i + len - 1) % len,
+        (None, true) => 0,
+        (None, false) => len - 1,
+    };
+    let (name, gains) = &presets[next];
+    app.player.equalizer.set_gains(*gains);
+    app.config.settings.eq_preset = Some(name.clone());
+}
+
+// keep the current bands as a new user preset
+fn save_preset(app: &mut App) {
+    let settings = &mut app.config.settings;
+    let mut n = settings.eq_presets.len() + 1;
+    while settings.eq_presets.contains_key(&format!("User {}", n)) {
+        n += 1;
+    }
+    let name = format!("User {}", n);
+    let bands = app.player.equalizer.gains().to_vec();
+    settings.eq_presets.insert(name.clone(), bands);
+    settings.eq_preset = Some(name.clone());
+    app.set_msg(&format!("Saved equalizer preset {}", name));
+}
+
+fn persist(app: &mut App) {
+    let eq = &app.player.equalizer;
+    let settings = &mut app.config.settings;
+    settings.eq_enabled = Some(eq.is_enabled());
+    settings.eq_bands = Some(eq.gains().to_vec());
+    if app.config.save().is_err() {
+        app.set_msg("Saving equalizer settings failed");
+    }
+}
+
+// the popup is modal like the device picker
+pub fn handle_equalizer(app: &mut App, code: KeyCode) -> bool {
+    let view = match &mut app.eq_view {
+        Some(view) => view,
+        None => {
+            if let KeyCode::Char('e') | KeyCode::Char('E') = code {
+                app.eq_view = Some(EqView { band: 0 });
+                return true;
+            }
+            return false;
+        }
+    };
+    let band = view.band;
+    match code {
+        KeyCode::Down => {
+            view.band = (band + 1) % BANDS;
+        }
+        KeyCode::Up => {
+            view.band = (band + BANDS - 1) % BANDS;
+        }
+        KeyCode::Right => {
+            // clamped to ±MAX_GAIN
+            app.player.equalizer.adjust(band, STEP);
+            edited(app);
+        }
+        KeyCode::Left => {
+            // clamped to ±MAX_GAIN
+            app.player.equalizer.adjust(band, -STEP);
+            edited(app);
+        }
+        KeyCode::Char('0') => {
+            let mut gains = app.player.equalizer.gains();
+            gains[band] = 0.0;
+            app.player.equalizer.set_gains(gains);
+            edited(app);
+        }
+        KeyCode::Char('p') => cycle_preset(app, true),
+        KeyCode::Char('P') => cycle_preset(app, false),
+        KeyCode::Char('t') | KeyCode::Char('T') => {
+            let eq = &app.player.equalizer;
+            eq.set_enabled(!eq.is_enabled());
+        }
+        KeyCode::Char('w') | KeyCode::Char('W') => {
+            save_preset(app);
+            persist(app);
+        }
+        KeyCode::Esc | KeyCode::Char('e') | KeyCode::Char('E') => {
+            app.eq_view = None;
+            persist(app);
+        }
+        _ => {}
+    }
+    true
+}
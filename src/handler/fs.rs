@@ -27,6 +27,7 @@ use crate::{
     media::{
         media::{Media, Source},
         player::Player,
+        playlist,
     },
 };
 
@@ -60,32 +61,45 @@ fn add_media_to_player(app: &mut App, once: bool) -> bool {
         } else {
             // 文件
             let entry = &fse.files[selected - fse.dirs.len() - 1];
-            let mut res = app.player.add_to_list(
+            let name = entry.file_name().to_string_lossy().to_string();
+            if playlist::is_playlist(name.as_str()) {
+                return add_playlist(app, name.as_str(), once);
+            }
+            let res = app.player.add_to_list(
                 Media {
                     src: Source::Local(entry.file_name().to_string_lossy().to_string()),
                 },
                 once,
             );
             // opt: add all music below
+            let mut failed = res.err();
             if once {
                 for i in selected - fse.dirs.len()..fse.files.len() {
                     let entry = &fse.files[i];
-                    res = app.player.add_to_list(
+                    if playlist::is_playlist(entry.file_name().to_string_lossy().as_ref()) {
+                        continue;
+                    }
+                    let res = app.player.add_to_list(
                         Media {
                             src: Source::Local(entry.file_name().to_string_lossy().to_string()),
                         },
                         false,
                     );
+                    if let Err(err) = res {
+                        failed.get_or_insert(err);
+                    }
                 }
             }
-            if !res {
-                let msg = format!("Open failed: {}", entry.file_name().to_str().unwrap());
-                app.set_msg(&msg);
-            } else {
-                let msg = format!("Start playing");
-                app.set_msg(&msg);
+            match failed {
+                Some(err) => {
+                    app.set_msg(&err.to_string());
+                    false
+                }
+                None => {
+                    app.set_msg("Start playing");
+                    true
+                }
             }
-            res
         }
     } else {
         fse.index.select(Some(0));
@@ -93,6 +107,79 @@ fn add_media_to_player(app: &mut App, once: bool) -> bool {
     }
 }
 
+// queue the entries of a playlist file, Enter replaces the queue with them
+fn add_playlist(app: &mut App, path: &str, once: bool) -> bool {
+    let entries = match playlist::read(path) {
+        Ok(entries) => entries,
+        Err(err) => {
+            app.set_msg(&format!("{}: {}", path, err));
+            return false;
+        }
+    };
+    let total = entries.len();
+    let mut added = 0;
+    let mut failed = None;
+    for media in entries {
+        // the queue is only cleared once something can be played
+        match app.player.add_to_list(media, once && added == 0) {
+            Ok(_) => added += 1,
+            Err(err) => {
+                failed.get_or_insert(err);
+            }
+        }
+    }
+    match failed {
+        Some(err) => {
+            app.set_msg(&format!("Added {} of {}, {}", added, total, err));
+            added > 0
+        }
+        None if total == 0 => {
+            app.set_msg("Empty playlist");
+            false
+        }
+        None => {
+            app.set_msg("Start playing");
+            true
+        }
+    }
+}
+
+// queue the selected file right after the current song
+fn play_next(app: &mut App) -> bool {
+    let fse = &app.fs;
+    let index = match fse.index.selected() {
+        Some(selected) if selected > fse.dirs.len() => selected - fse.dirs.len() - 1,
+        _ => return false,
+    };
+    let entry = &fse.files[index];
+    let res = app.player.insert_next(Media {
+        src: Source::Local(entry.file_name().to_string_lossy().to_string()),
+    });
+    match res {
+        Ok(_) => app.set_msg("Playing next"),
+        Err(err) => app.set_msg(&err.to_string()),
+    }
+    true
+}
+
+// queue the selected file split at its pauses, one item per sentence
+fn add_segments(app: &mut App) -> bool {
+    let fse = &app.fs;
+    let index = match fse.index.selected() {
+        Some(selected) if selected > fse.dirs.len() => selected - fse.dirs.len() - 1,
+        _ => return false,
+    };
+    let entry = &fse.files[index];
+    let res = app.player.add_segments(Media {
+        src: Source::Local(entry.file_name().to_string_lossy().to_string()),
+    });
+    match res {
+        Ok(_) => app.set_msg("Splitting into sentences"),
+        Err(err) => app.set_msg(&err.to_string()),
+    }
+    true
+}
+
 pub fn handle_fs(app: &mut App, key: KeyCode) -> bool {
     let fse = &mut app.fs;
     let len = fse.dirs.len() + fse.files.len();
@@ -127,6 +214,12 @@ pub fn handle_fs(app: &mut App, key: KeyCode) -> bool {
         KeyCode::Enter => {
             add_media_to_player(app, true);
         }
+        KeyCode::Char('p') | KeyCode::Char('P') => {
+            return play_next(app);
+        }
+        KeyCode::Char('v') | KeyCode::Char('V') => {
+            return add_segments(app);
+        }
         KeyCode::Esc => {
             let dir = current_dir().unwrap();
             match dir.parent() {
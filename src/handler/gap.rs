@@ -1,20 +1,40 @@
 use crossterm::event::KeyCode;
 
-use crate::{app::App, media::player::Player};
+use crate::{
+    app::App,
+    media::player::{GapMode, Player, Transition},
+};
+
+use super::report;
 
 pub fn handle_gap(app: &mut App, code: KeyCode) -> bool {
     let player = &mut app.player;
-    match code {
-        KeyCode::Char('j') | KeyCode::Char('J') => {
-            player.adjust_gap(-0.5);
-            return true;
-        }
-        KeyCode::Char('k') | KeyCode::Char('K') => {
-            player.adjust_gap(0.5);
+    let step = match code {
+        KeyCode::Char('j') | KeyCode::Char('J') => -0.5,
+        KeyCode::Char('k') | KeyCode::Char('K') => 0.5,
+        KeyCode::Char('f') | KeyCode::Char('F') => {
+            let res = player.switch_transition();
+            report(app, res);
             return true;
         }
         _ => {
             return false;
         }
-    }
-}
\ No newline at end of file
+    };
+    let res = match (player.transition, player.gap_mode, code) {
+        (Transition::Crossfade, _, _) => player.adjust_crossfade(step),
+        // j/k scale the gap with the song, J/K move the offset
+        (Transition::Gap, GapMode::Shadowing, KeyCode::Char('j'))
+        | (Transition::Gap, GapMode::Shadowing, KeyCode::Char('k')) => {
+            player.adjust_gap_factor(step / 2.0)
+        }
+        // the offset of the song being shadowed, the gap after it follows at once
+        (Transition::Gap, GapMode::Shadowing, _) => match player.play_list.current {
+            Some(current) => player.adjust_item_gap(current, step),
+            None => player.adjust_gap(step),
+        },
+        (Transition::Gap, GapMode::Fixed, _) => player.adjust_gap(step),
+    };
+    report(app, res);
+    true
+}
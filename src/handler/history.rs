@@ -0,0 +1,52 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use crossterm::event::KeyCode;
+use tui::widgets::ListState;
+
+use crate::{app::App, ui::history::HistoryView};
+
+// the view is modal like the device picker
+pub fn handle_history(app: &mut App, code: KeyCode) -> bool {
+    let view = match &mut app.history_view {
+        Some(view) => view,
+        None => {
+            if let KeyCode::Char('l') | KeyCode::Char('L') = code {
+                let mut state = ListState::default();
+                state.select(Some(0));
+                app.history_view = Some(HistoryView { state });
+                return true;
+            }
+            return false;
+        }
+    };
+    let len = app.player.history.len().max(1);
+    let selected = view.state.selected().unwrap_or(0).min(len - 1);
+    match code {
+        KeyCode::Down => {
+            view.state.select(Some((selected + 1) % len));
+        }
+        KeyCode::Up => {
+            view.state.select(Some((selected + len - 1) % len));
+        }
+        KeyCode::Esc | KeyCode::Char('l') | KeyCode::Char('L') => {
+            app.history_view = None;
+        }
+        _ => {}
+    }
+    true
+}
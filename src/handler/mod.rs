@@ -17,24 +17,61 @@
 
 use crossterm::event::KeyCode;
 
-use crate::app::App;
+use crate::{
+    app::{App, Focus},
+    media::player::PlayerError,
+};
 
 use self::{
-    fs::handle_fs,
-    music_controller::handle_music_controller,
-    player::handle_player,
-    gap::handle_gap,
-    repetition::handle_repetition,
+    ab_loop::handle_ab_loop, channels::handle_channels, device::handle_device,
+    equalizer::handle_equalizer, fs::handle_fs, gap::handle_gap, history::handle_history,
+    music_controller::handle_music_controller, play_list::handle_play_list, player::handle_player,
+    repetition::handle_repetition, save::handle_save, seek::handle_seek, sleep::handle_sleep,
+    speed::handle_speed, station_log::handle_station_log,
 };
 
+mod ab_loop;
+mod channels;
+mod device;
+mod equalizer;
 mod fs;
+mod gap;
+mod history;
 mod music_controller;
+mod play_list;
 mod player;
 mod repetition;
-mod gap;
+mod save;
+mod seek;
+mod sleep;
+mod speed;
+mod station_log;
 
 pub fn handle_keyboard_event(app: &mut App, key: KeyCode) {
-    if handle_fs(app, key) {
+    if handle_device(app, key) {
+        return;
+    }
+    if handle_history(app, key) {
+        return;
+    }
+    if handle_equalizer(app, key) {
+        return;
+    }
+    if handle_station_log(app, key) {
+        return;
+    }
+    if key == KeyCode::Tab {
+        app.focus = match app.focus {
+            Focus::Explorer => Focus::Playlist,
+            Focus::Playlist => Focus::Explorer,
+        };
+        return;
+    }
+    let handled = match app.focus {
+        Focus::Explorer => handle_fs(app, key),
+        Focus::Playlist => handle_play_list(app, key),
+    };
+    if handled {
         return;
     }
     if handle_player(app, key) {
@@ -49,4 +86,27 @@ pub fn handle_keyboard_event(app: &mut App, key: KeyCode) {
     if handle_gap(app, key) {
         return;
     }
+    if handle_speed(app, key) {
+        return;
+    }
+    if handle_seek(app, key) {
+        return;
+    }
+    if handle_ab_loop(app, key) {
+        return;
+    }
+    if handle_channels(app, key) {
+        return;
+    }
+    if handle_sleep(app, key) {
+        return;
+    }
+    handle_save(app, key);
+}
+
+// show what went wrong in the header instead of giving up
+fn report(app: &mut App, res: Result<(), PlayerError>) {
+    if let Err(err) = res {
+        app.set_msg(&err.to_string());
+    }
 }
@@ -19,27 +19,27 @@ use crossterm::event::KeyCode;
 
 use crate::{app::App, media::player::Player};
 
+use super::report;
+
 pub fn handle_music_controller(app: &mut App, code: KeyCode) -> bool {
     let player = &mut app.player;
-    match code {
+    let res = match code {
         KeyCode::Char(' ') => {
             if player.is_playing() {
-                player.pause();
+                player.pause()
             } else {
-                player.resume();
+                player.resume()
             }
-            return true;
-        }
-        KeyCode::Char('n') | KeyCode::Char('N') => {
-            player.next();
-            return true;
-        }
-        KeyCode::Char('c') | KeyCode::Char('C') => {
-            player.clear_list();
-            return true;
         }
+        KeyCode::Char('n') => player.next(),
+        KeyCode::Char('N') => player.prev(),
+        KeyCode::Char('c') | KeyCode::Char('C') => player.clear_list(),
+        KeyCode::Char('r') | KeyCode::Char('R') => player.switch_repeat(),
+        KeyCode::Char('s') | KeyCode::Char('S') => player.toggle_shuffle(),
         _ => {
             return false;
         }
-    }
+    };
+    report(app, res);
+    true
 }
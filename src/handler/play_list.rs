@@ -0,0 +1,95 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use crossterm::event::KeyCode;
+
+use crate::{
+    app::{App, Focus},
+    media::player::Player,
+};
+
+use super::report;
+
+pub fn handle_play_list(app: &mut App, code: KeyCode) -> bool {
+    let len = app.player.play_list.lists.len();
+    let state = &mut app.music_controller.state;
+    let selected = match state.selected() {
+        Some(selected) if selected < len => Some(selected),
+        _ => None,
+    };
+    let res = match (code, selected) {
+        (KeyCode::Esc, _) => {
+            app.focus = Focus::Explorer;
+            return true;
+        }
+        (KeyCode::Down, _) | (KeyCode::Up, _) if len == 0 => {
+            state.select(None);
+            return true;
+        }
+        (KeyCode::Down, Some(selected)) => {
+            state.select(Some((selected + 1) % len));
+            return true;
+        }
+        (KeyCode::Up, Some(selected)) => {
+            state.select(Some((selected + len - 1) % len));
+            return true;
+        }
+        (KeyCode::Down, None) | (KeyCode::Up, None) => {
+            state.select(app.player.play_list.current.or(Some(0)));
+            return true;
+        }
+        (KeyCode::Enter, Some(selected)) => app.player.play_at(selected),
+        (KeyCode::Delete, Some(selected)) | (KeyCode::Backspace, Some(selected)) => {
+            if selected + 1 == len && selected > 0 {
+                state.select(Some(selected - 1));
+            }
+            app.player.remove_at(selected)
+        }
+        (KeyCode::Char('g'), Some(selected)) | (KeyCode::Char('G'), Some(selected)) => {
+            app.player.adjust_item_repetition(selected, false)
+        }
+        (KeyCode::Char('h'), Some(selected)) | (KeyCode::Char('H'), Some(selected)) => {
+            app.player.adjust_item_repetition(selected, true)
+        }
+        (KeyCode::Char('j'), Some(selected)) | (KeyCode::Char('J'), Some(selected)) => {
+            app.player.adjust_item_gap(selected, -0.5)
+        }
+        (KeyCode::Char('k'), Some(selected)) | (KeyCode::Char('K'), Some(selected)) => {
+            app.player.adjust_item_gap(selected, 0.5)
+        }
+        (KeyCode::Char('['), Some(selected)) if selected > 0 => {
+            state.select(Some(selected - 1));
+            app.player.move_item(selected, true)
+        }
+        (KeyCode::Char(']'), Some(selected)) if selected + 1 < len => {
+            state.select(Some(selected + 1));
+            app.player.move_item(selected, false)
+        }
+        (KeyCode::Enter, None)
+        | (KeyCode::Delete, None)
+        | (KeyCode::Backspace, None)
+        | (KeyCode::Char('['), _)
+        | (KeyCode::Char(']'), _) => {
+            return true;
+        }
+        _ => {
+            return false;
+        }
+    };
+    report(app, res);
+    true
+}
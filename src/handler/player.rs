@@ -19,22 +19,24 @@ use crossterm::event::KeyCode;
 
 use crate::{app::App, media::player::Player};
 
+use super::report;
+
 pub fn handle_player(app: &mut App, code: KeyCode) -> bool {
-    match code {
+    let res = match code {
         KeyCode::Char('-') => {
             let volume = app.player.volume() - 0.05;
             let new_volume = volume.max(0.0);
-            app.player.set_volume(new_volume);
-            return true;
+            app.player.set_volume(new_volume)
         }
         KeyCode::Char('=') | KeyCode::Char('+') => {
             let volume = app.player.volume() + 0.05;
             let new_volume = volume.min(1.0);
-            app.player.set_volume(new_volume);
-            return true;
+            app.player.set_volume(new_volume)
         }
         _ => {
             return false;
         }
-    }
+    };
+    report(app, res);
+    true
 }
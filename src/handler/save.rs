@@ -0,0 +1,41 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use chrono::Local;
+use crossterm::event::KeyCode;
+
+use crate::{app::App, media::player::Player};
+
+// save the queue as an M3U8 in the explorer's folder
+pub fn handle_save(app: &mut App, code: KeyCode) -> bool {
+    if code != KeyCode::F(2) {
+        return false;
+    }
+    if app.player.play_list.lists.is_empty() {
+        app.set_msg("Play list is empty");
+        return true;
+    }
+    let name = format!("queue-{}.m3u8", Local::now().format("%Y%m%d-%H%M%S"));
+    match app.player.save_playlist(name.as_str()) {
+        Ok(_) => {
+            app.set_msg(&format!("Saved {}", name));
+            app.fs.refresh();
+        }
+        Err(err) => app.set_msg(&err.to_string()),
+    }
+    true
+}
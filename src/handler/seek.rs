@@ -0,0 +1,53 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+use crossterm::event::KeyCode;
+
+use crate::{app::App, media::player::Player};
+
+use super::report;
+
+pub fn handle_seek(app: &mut App, code: KeyCode) -> bool {
+    let player = &mut app.player;
+    let res = match code {
+        KeyCode::Char(',') => player.seek_by(-5),
+        KeyCode::Char('.') => player.seek_by(5),
+        KeyCode::Char('<') => player.seek_by(-30),
+        KeyCode::Char('>') => player.seek_by(30),
+        KeyCode::Char(c) if c.is_ascii_digit() => {
+            let length = match player.playing_song() {
+                Some(item) if !item.is_stream() => item.duration.unwrap_or_default(),
+                _ => Duration::from_secs(0),
+            };
+            if length.is_zero() {
+                // a station, or a file still being probed: there is no 90% to jump to
+                return true;
+            }
+            // 0-9 => 0%-90%
+            let percent = c.to_digit(10).unwrap() as f64 / 10.0;
+            let target = length.mul_f64(percent);
+            player.seek_to(target)
+        }
+        _ => {
+            return false;
+        }
+    };
+    report(app, res);
+    true
+}
@@ -0,0 +1,36 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use crossterm::event::KeyCode;
+
+use crate::app::App;
+
+use super::report;
+
+pub fn handle_sleep(app: &mut App, code: KeyCode) -> bool {
+    let player = &mut app.player;
+    let res = match code {
+        KeyCode::Char('t') => app.sleep.extend(player),
+        KeyCode::Char('T') => app.sleep.after_current(player),
+        KeyCode::Char('z') | KeyCode::Char('Z') => app.sleep.cancel(player),
+        _ => {
+            return false;
+        }
+    };
+    report(app, res);
+    true
+}
@@ -0,0 +1,62 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use crossterm::event::KeyCode;
+use tui::widgets::ListState;
+
+use crate::{app::App, ui::station_log::StationLogView};
+
+// titles recently played on the current station, modal like the history
+pub fn handle_station_log(app: &mut App, code: KeyCode) -> bool {
+    let view = match &mut app.station_log {
+        Some(view) => view,
+        None => {
+            if let KeyCode::Char('y') | KeyCode::Char('Y') = code {
+                match app.player.playing_song() {
+                    Some(item) if item.meta.is_some() => {
+                        let mut state = ListState::default();
+                        state.select(Some(0));
+                        app.station_log = Some(StationLogView { state });
+                    }
+                    _ => app.set_msg("Not playing a station"),
+                }
+                return true;
+            }
+            return false;
+        }
+    };
+    let len = app
+        .player
+        .playing_song()
+        .and_then(|item| item.meta.as_ref())
+        .map_or(0, |meta| meta.log().len())
+        .max(1);
+    let selected = view.state.selected().unwrap_or(0).min(len - 1);
+    match code {
+        KeyCode::Down => {
+            view.state.select(Some((selected + 1) % len));
+        }
+        KeyCode::Up => {
+            view.state.select(Some((selected + len - 1) % len));
+        }
+        KeyCode::Esc | KeyCode::Char('y') | KeyCode::Char('Y') => {
+            app.station_log = None;
+        }
+        _ => {}
+    }
+    true
+}
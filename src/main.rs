@@ -23,6 +23,7 @@ mod app;
 mod config;
 mod handler;
 mod media;
+mod sleep;
 mod ui;
 
 
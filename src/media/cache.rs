@@ -0,0 +1,235 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant, UNIX_EPOCH},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+// results are written out at most this long after they came in, or when the queue runs dry
+const SAVE_EVERY: Duration = Duration::from_secs(2);
+
+/// How soon a job runs, jobs of the same priority run in the order they were asked for.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Priority {
+    // something waits for it
+    High,
+    Normal,
+    // nice to know, nothing depends on it
+    Low,
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+// each writes a table out if it has changed
+type Tables = Arc<Mutex<Vec<Box<dyn Fn() + Send>>>>;
+
+/// The one thread every cache finds things out about files on, and which writes
+/// the tables out.
+#[derive(Clone)]
+pub struct Worker {
+    sender: Sender<(Priority, Job)>,
+    tables: Tables,
+}
+
+impl Worker {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let tables = Tables::default();
+        let saved = tables.clone();
+        thread::spawn(move || work(receiver, saved));
+        Self { sender, tables }
+    }
+
+    /// run `job` once the more urgent ones and those asked for before are done
+    pub fn run<F>(&self, priority: Priority, job: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let _ = self.sender.send((priority, Box::new(job)));
+    }
+
+    /// write out what changed right away, before the app exits
+    pub fn flush(&self) {
+        save(&self.tables);
+    }
+}
+
+fn work(receiver: Receiver<(Priority, Job)>, tables: Tables) {
+    let mut queue: Vec<(Priority, Job)> = vec![];
+    let mut saved = Instant::now();
+    loop {
+        let received = match queue.is_empty() {
+            true => {
+                // idle, write out what changed
+                save(&tables);
+                match receiver.recv() {
+                    Ok(job) => Some(job),
+                    // the player is gone
+                    Err(_) => return,
+                }
+            }
+            false => receiver.try_recv().ok(),
+        };
+        if let Some(job) = received {
+            queue.push(job);
+            continue;
+        }
+        // the most urgent, the first of them
+        let next = (0..queue.len()).min_by_key(|i| queue[*i].0).unwrap();
+        let (_, job) = queue.remove(next);
+        job();
+        if saved.elapsed() >= SAVE_EVERY {
+            save(&tables);
+            saved = Instant::now();
+        }
+    }
+}
+
+fn save(tables: &Tables) {
+    tables.lock().unwrap().iter().for_each(|save| save());
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Entry<V> {
+    path: String,
+    mtime: u64,
+    size: u64,
+    #[serde(flatten)]
+    value: V,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile<V> {
+    #[serde(default = "Vec::new")]
+    entries: Vec<Entry<V>>,
+}
+
+struct Table<V> {
+    entries: HashMap<String, Entry<V>>,
+    // changed since it was last written
+    dirty: bool,
+}
+
+/// Something found out about files, kept in `<cache dir>/rustplayer/<name>.toml`
+/// until the file changes. The [`Worker`] writes it out every so often.
+#[derive(Clone)]
+pub struct FileCache<V> {
+    table: Arc<Mutex<Table<V>>>,
+}
+
+impl<V> FileCache<V>
+where
+    V: Serialize + DeserializeOwned + Clone + Send + 'static,
+{
+    pub fn new(name: &'static str, worker: &Worker) -> Self {
+        let entries = cache_path(name)
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|s| toml::from_str::<CacheFile<V>>(s.as_str()).ok())
+            .map_or(vec![], |file| file.entries);
+        let table = Arc::new(Mutex::new(Table {
+            entries: entries
+                .into_iter()
+                .map(|entry| (entry.path.clone(), entry))
+                .collect(),
+            dirty: false,
+        }));
+        let saved = table.clone();
+        let save = move || {
+            // written without holding up `get`
+            let changes = saved.lock().unwrap().changes();
+            if let Some(file) = changes {
+                write(name, &file);
+            }
+        };
+        worker.tables.lock().unwrap().push(Box::new(save));
+        Self { table }
+    }
+
+    /// cached value of `path`, if the file has not changed since
+    pub fn get(&self, path: &str) -> Option<V> {
+        let stamp = file_stamp(path)?;
+        let table = self.table.lock().unwrap();
+        match table.entries.get(path) {
+            Some(entry) if (entry.mtime, entry.size) == stamp => Some(entry.value.clone()),
+            _ => None,
+        }
+    }
+
+    /// keep `value` for `path` as the file is now
+    pub fn insert(&self, path: &str, value: V) {
+        let (mtime, size) = match file_stamp(path) {
+            Some(stamp) => stamp,
+            None => return,
+        };
+        let mut table = self.table.lock().unwrap();
+        let entry = Entry {
+            path: path.to_string(),
+            mtime,
+            size,
+            value,
+        };
+        table.entries.insert(path.to_string(), entry);
+        table.dirty = true;
+    }
+}
+
+impl<V: Clone> Table<V> {
+    // everything to write, if anything changed since the last time
+    fn changes(&mut self) -> Option<CacheFile<V>> {
+        if !self.dirty {
+            return None;
+        }
+        self.dirty = false;
+        Some(CacheFile {
+            entries: self.entries.values().cloned().collect(),
+        })
+    }
+}
+
+fn write<V: Serialize>(name: &str, file: &CacheFile<V>) {
+    let path = match cache_path(name) {
+        Some(path) => path,
+        None => return,
+    };
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+    if let Ok(s) = toml::to_string(file) {
+        let _ = fs::write(path, s);
+    }
+}
+
+fn cache_path(name: &str) -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("rustplayer").join(format!("{}.toml", name)))
+}
+
+// mtime and size, a changed file is worked on again
+fn file_stamp(path: &str) -> Option<(u64, u64)> {
+    let meta = fs::metadata(path).ok()?;
+    let mtime = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((mtime.as_secs(), meta.len()))
+}
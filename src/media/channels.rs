@@ -0,0 +1,267 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    f32::consts::PI,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use rodio::Source;
+
+// low pass of the signal fed to the other ear
+const CROSSFEED_CUTOFF: f32 = 700.0;
+// about -6 dB
+const CROSSFEED_LEVEL: f32 = 0.5;
+
+/// Channel settings shared with every [`ChannelMixer`] in the chain.
+#[derive(Clone)]
+pub struct ChannelControl {
+    inner: Arc<ChannelInner>,
+}
+
+struct ChannelInner {
+    // -1.0 is left only, 1.0 right only
+    balance: AtomicU32,
+    mono: AtomicBool,
+    swap: AtomicBool,
+    crossfeed: AtomicBool,
+}
+
+impl ChannelControl {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(ChannelInner {
+                balance: AtomicU32::new(0f32.to_bits()),
+                mono: AtomicBool::new(false),
+                swap: AtomicBool::new(false),
+                crossfeed: AtomicBool::new(false),
+            }),
+        }
+    }
+
+    pub fn balance(&self) -> f32 {
+        f32::from_bits(self.inner.balance.load(Ordering::Relaxed))
+    }
+
+    pub fn set_balance(&self, balance: f32) {
+        let balance = balance.clamp(-1.0, 1.0);
+        self.inner
+            .balance
+            .store(balance.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn is_mono(&self) -> bool {
+        self.inner.mono.load(Ordering::Relaxed)
+    }
+
+    pub fn toggle_mono(&self) {
+        self.inner.mono.fetch_xor(true, Ordering::Relaxed);
+    }
+
+    pub fn is_swapped(&self) -> bool {
+        self.inner.swap.load(Ordering::Relaxed)
+    }
+
+    pub fn toggle_swap(&self) {
+        self.inner.swap.fetch_xor(true, Ordering::Relaxed);
+    }
+
+    pub fn is_crossfeed(&self) -> bool {
+        self.inner.crossfeed.load(Ordering::Relaxed)
+    }
+
+    pub fn toggle_crossfeed(&self) {
+        self.inner.crossfeed.fetch_xor(true, Ordering::Relaxed);
+    }
+}
+
+/// Swap, mono downmix, crossfeed and balance, in that order, on stereo input.
+/// Other layouts are passed through.
+pub struct ChannelMixer<I> {
+    input: I,
+    control: ChannelControl,
+    // right sample of the frame being output
+    pending: Option<f32>,
+    // low passed left and right for the crossfeed
+    lowpass: [f32; 2],
+    coefficient: f32,
+}
+
+impl<I> ChannelMixer<I>
+where
+    I: Source<Item = f32>,
+{
+    pub fn new(input: I, control: ChannelControl) -> Self {
+        let rate = input.sample_rate() as f32;
+        Self {
+            input,
+            control,
+            pending: None,
+            lowpass: [0.0; 2],
+            coefficient: 1.0 - (-2.0 * PI * CROSSFEED_CUTOFF / rate).exp(),
+        }
+    }
+
+    fn process(&mut self, mut left: f32, mut right: f32) -> (f32, f32) {
+        let control = &self.control;
+        if control.is_swapped() {
+            (left, right) = (right, left);
+        }
+        if control.is_mono() {
+            let mid = (left + right) / 2.0;
+            left = mid;
+            right = mid;
+        }
+        // keep the filters running so turning crossfeed on does not click
+        self.lowpass[0] += self.coefficient * (left - self.lowpass[0]);
+        self.lowpass[1] += self.coefficient * (right - self.lowpass[1]);
+        if control.is_crossfeed() {
+            let norm = 1.0 / (1.0 + CROSSFEED_LEVEL);
+            let (l, r) = (left, right);
+            left = (l + CROSSFEED_LEVEL * self.lowpass[1]) * norm;
+            right = (r + CROSSFEED_LEVEL * self.lowpass[0]) * norm;
+        }
+        let balance = control.balance();
+        if balance > 0.0 {
+            left *= 1.0 - balance;
+        } else if balance < 0.0 {
+            right *= 1.0 + balance;
+        }
+        (left, right)
+    }
+}
+
+impl<I> Iterator for ChannelMixer<I>
+where
+    I: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if let Some(right) = self.pending.take() {
+            return Some(right);
+        }
+        if self.input.channels() != 2 {
+            return self.input.next();
+        }
+        let left = self.input.next()?;
+        let right = self.input.next().unwrap_or(0.0);
+        let (left, right) = self.process(left, right);
+        self.pending = Some(right);
+        Some(left)
+    }
+}
+
+impl<I> Source for ChannelMixer<I>
+where
+    I: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rodio::buffer::SamplesBuffer;
+
+    use super::{ChannelControl, ChannelMixer, CROSSFEED_LEVEL};
+
+    // (left, right) frames through a mixer
+    fn mix(control: &ChannelControl, frames: &[(f32, f32)]) -> Vec<(f32, f32)> {
+        let samples: Vec<f32> = frames.iter().flat_map(|(l, r)| [*l, *r]).collect();
+        let mixer = ChannelMixer::new(SamplesBuffer::new(2, 44100, samples), control.clone());
+        let out: Vec<f32> = mixer.collect();
+        out.chunks(2).map(|frame| (frame[0], frame[1])).collect()
+    }
+
+    const FRAMES: [(f32, f32); 3] = [(0.5, -0.25), (0.2, 0.4), (-1.0, 0.0)];
+
+    #[test]
+    fn passes_through_by_default() {
+        assert_eq!(mix(&ChannelControl::new(), &FRAMES), FRAMES);
+    }
+
+    #[test]
+    fn swaps_and_downmixes() {
+        let control = ChannelControl::new();
+        control.toggle_swap();
+        assert_eq!(
+            mix(&control, &FRAMES),
+            [(-0.25, 0.5), (0.4, 0.2), (0.0, -1.0)]
+        );
+        control.toggle_swap();
+        control.toggle_mono();
+        assert_eq!(
+            mix(&control, &FRAMES),
+            [(0.125, 0.125), (0.3, 0.3), (-0.5, -0.5)]
+        );
+    }
+
+    #[test]
+    fn balance_turns_down_the_other_side() {
+        let control = ChannelControl::new();
+        control.set_balance(0.5);
+        assert_eq!(mix(&control, &[(0.5, 0.5)]), [(0.25, 0.5)]);
+        control.set_balance(-3.0);
+        assert_eq!(control.balance(), -1.0);
+        assert_eq!(mix(&control, &[(0.5, 0.5)]), [(0.5, 0.0)]);
+    }
+
+    #[test]
+    fn crossfeeds_the_lows() {
+        let control = ChannelControl::new();
+        control.toggle_crossfeed();
+        // a steady left only signal, once the low pass has caught up
+        let out = mix(&control, &[(0.6, 0.0); 2000]);
+        let (left, right) = out[1999];
+        let norm = 1.0 + CROSSFEED_LEVEL;
+        assert!((left - 0.6 / norm).abs() < 1e-3, "{}", left);
+        let crossed = 0.6 * CROSSFEED_LEVEL / norm;
+        assert!((right - crossed).abs() < 1e-3, "{}", right);
+        // the highest frequency barely gets across
+        let nyquist: Vec<(f32, f32)> = (0..2000).map(|i| ([0.6, -0.6][i % 2], 0.0)).collect();
+        let out = mix(&control, &nyquist);
+        assert!(out[1000..].iter().all(|(_, right)| right.abs() < 0.02));
+    }
+
+    #[test]
+    fn leaves_mono_alone() {
+        let control = ChannelControl::new();
+        control.toggle_swap();
+        control.set_balance(1.0);
+        let samples = vec![0.1, 0.2, 0.3];
+        let mixer = ChannelMixer::new(SamplesBuffer::new(1, 44100, samples.clone()), control);
+        assert_eq!(mixer.collect::<Vec<f32>>(), samples);
+    }
+}
@@ -0,0 +1,543 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{fs::File, path::Path, time::Duration};
+
+use rodio::Source;
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::{self, CodecParameters, DecoderOptions, CODEC_TYPE_NULL},
+    errors::Error,
+    formats::{FormatOptions, FormatReader, Packet, SeekMode, SeekTo},
+    io::{MediaSource, MediaSourceStream},
+    meta::MetadataOptions,
+    probe::Hint,
+    units::Time,
+};
+
+use super::player::PlayerError;
+
+// a bad packet is skipped, this many in a row end the track
+const MAX_DECODE_ERRORS: usize = 3;
+
+/// A decoded file, whatever the format.
+pub type Track = Box<dyn Source<Item = i16> + Send>;
+
+/// Extensions of the formats the compiled-in decoders can read.
+pub fn extensions() -> Vec<&'static str> {
+    let mut extensions = vec!["mp3", "wav", "flac"];
+    if cfg!(feature = "vorbis") {
+        extensions.extend(["ogg", "oga"]);
+    }
+    if cfg!(feature = "aac") {
+        extensions.extend(["m4a", "mp4", "aac"]);
+    }
+    if cfg!(feature = "alac") && !extensions.contains(&"m4a") {
+        extensions.push("m4a");
+    }
+    if cfg!(feature = "opus") {
+        extensions.push("opus");
+    }
+    extensions
+}
+
+pub fn open(path: &str) -> Result<Track, PlayerError> {
+    open_at(path, Duration::from_secs(0))
+}
+
+/// a file positioned at `start`, by seeking rather than decoding up to it
+pub fn open_at(path: &str, start: Duration) -> Result<Track, PlayerError> {
+    let f = match File::open(path) {
+        Ok(f) => f,
+        Err(_) => return Err(PlayerError::FileMissing(path.to_string())),
+    };
+    let extension = Path::new(path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase());
+    let mut decoder = MediaDecoder::new(Box::new(f), extension.as_deref())
+        .map_err(|err| decode_error(path, err))?;
+    if !start.is_zero() {
+        decoder.seek(start);
+    }
+    Ok(Box::new(decoder))
+}
+
+pub fn decode_error(path: &str, err: Error) -> PlayerError {
+    match err {
+        Error::Unsupported(_) => PlayerError::UnsupportedFormat(path.to_string()),
+        err => PlayerError::Decode(path.to_string(), err.to_string()),
+    }
+}
+
+enum Codec {
+    Symphonia(Box<dyn codecs::Decoder>),
+    #[cfg(feature = "opus")]
+    Opus(super::opus::OpusCodec),
+}
+
+impl Codec {
+    fn new(params: &CodecParameters) -> Result<Self, Error> {
+        #[cfg(feature = "opus")]
+        if params.codec == codecs::CODEC_TYPE_OPUS {
+            let channels = params.channels.map_or(2, |channels| channels.count());
+            return super::opus::OpusCodec::new(channels)
+                .map(Codec::Opus)
+                .ok_or(Error::Unsupported("opus channel layout"));
+        }
+        let decoder = symphonia::default::get_codecs().make(params, &DecoderOptions::default())?;
+        Ok(Codec::Symphonia(decoder))
+    }
+
+    // interleaved samples of `packet` into `out`, returns their channels and rate
+    fn decode(&mut self, packet: &Packet, out: &mut Vec<i16>) -> Result<(u16, u32), Error> {
+        match self {
+            Codec::Symphonia(decoder) => {
+                let decoded = decoder.decode(packet)?;
+                let spec = *decoded.spec();
+                let mut samples = SampleBuffer::<i16>::new(decoded.capacity() as u64, spec);
+                samples.copy_interleaved_ref(decoded);
+                out.clear();
+                out.extend_from_slice(samples.samples());
+                Ok((spec.channels.count() as u16, spec.rate))
+            }
+            #[cfg(feature = "opus")]
+            Codec::Opus(codec) => codec.decode(packet.buf(), out),
+        }
+    }
+
+    fn reset(&mut self) {
+        match self {
+            Codec::Symphonia(decoder) => decoder.reset(),
+            #[cfg(feature = "opus")]
+            Codec::Opus(codec) => codec.reset(),
+        }
+    }
+}
+
+/// Default track of a container, decoded packet by packet.
+pub struct MediaDecoder {
+    format: Box<dyn FormatReader>,
+    codec: Codec,
+    track: u32,
+    channels: u16,
+    sample_rate: u32,
+    duration: Option<Duration>,
+    buffer: Vec<i16>,
+    pos: usize,
+    // samples still to drop, the decoder primes itself or lands before a seek target
+    skip: usize,
+    ended: bool,
+}
+
+impl MediaDecoder {
+    pub fn new(source: Box<dyn MediaSource>, extension: Option<&str>) -> Result<Self, Error> {
+        let mss = MediaSourceStream::new(source, Default::default());
+        let mut hint = Hint::new();
+        if let Some(extension) = extension {
+            hint.with_extension(extension);
+        }
+        let probed = symphonia::default::get_probe().format(
+            &hint,
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )?;
+        let format = probed.format;
+        let track = format
+            .tracks()
+            .iter()
+            .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or(Error::Unsupported("no audio track"))?;
+        let params = track.codec_params.clone();
+        let channels = params.channels.map_or(2, |channels| channels.count()) as u16;
+        let duration = match (params.n_frames, params.time_base, params.sample_rate) {
+            (Some(frames), Some(time_base), _) => Some(to_duration(time_base.calc_time(frames))),
+            (Some(frames), None, Some(rate)) => {
+                Some(Duration::from_secs_f64(frames as f64 / rate as f64))
+            }
+            _ => None,
+        };
+        let mut decoder = Self {
+            track: track.id,
+            codec: Codec::new(&params)?,
+            format,
+            channels,
+            sample_rate: params.sample_rate.unwrap_or(44100),
+            duration,
+            buffer: vec![],
+            pos: 0,
+            skip: params.delay.unwrap_or(0) as usize * channels as usize,
+            ended: false,
+        };
+        // the first packet tells the real channels and rate
+        decoder.ended = !decoder.refill();
+        Ok(decoder)
+    }
+
+    /// continue from `to`, a position past the end ends the track
+    pub fn seek(&mut self, to: Duration) {
+        let seeked = self.format.seek(
+            SeekMode::Accurate,
+            SeekTo::Time {
+                time: Time::from(to.as_secs_f64()),
+                track_id: Some(self.track),
+            },
+        );
+        self.codec.reset();
+        self.buffer.clear();
+        self.pos = 0;
+        match seeked {
+            Ok(seeked) => {
+                // the reader stops at a packet boundary before the target
+                let frames = seeked.required_ts.saturating_sub(seeked.actual_ts);
+                self.skip = frames as usize * self.channels as usize;
+                self.ended = !self.refill();
+            }
+            Err(_) => self.ended = true,
+        }
+    }
+
+    /// the samples left of the current packet, None at the end
+    pub fn next_chunk(&mut self) -> Option<Vec<i16>> {
+        if self.pos >= self.buffer.len() && (self.ended || !self.refill()) {
+            self.ended = true;
+            return None;
+        }
+        let chunk = self.buffer[self.pos..].to_vec();
+        self.pos = self.buffer.len();
+        Some(chunk)
+    }
+
+    // decode packets until one has samples to play, false at the end
+    fn refill(&mut self) -> bool {
+        let mut errors = 0;
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(_) => return false,
+            };
+            if packet.track_id() != self.track {
+                continue;
+            }
+            match self.codec.decode(&packet, &mut self.buffer) {
+                Ok((channels, sample_rate)) => {
+                    errors = 0;
+                    self.channels = channels;
+                    self.sample_rate = sample_rate;
+                    let skipped = self.skip.min(self.buffer.len());
+                    self.skip -= skipped;
+                    self.pos = skipped;
+                    if self.pos < self.buffer.len() {
+                        return true;
+                    }
+                }
+                Err(Error::DecodeError(_)) if errors < MAX_DECODE_ERRORS => errors += 1,
+                Err(_) => return false,
+            }
+        }
+    }
+}
+
+fn to_duration(time: Time) -> Duration {
+    Duration::from_secs(time.seconds) + Duration::from_secs_f64(time.frac)
+}
+
+impl Iterator for MediaDecoder {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if self.pos >= self.buffer.len() && (self.ended || !self.refill()) {
+            self.ended = true;
+            return None;
+        }
+        self.pos += 1;
+        Some(self.buffer[self.pos - 1])
+    }
+}
+
+impl Source for MediaDecoder {
+    fn current_frame_len(&self) -> Option<usize> {
+        // the next packet may change channels or rate
+        match self.buffer.len() - self.pos {
+            0 => None,
+            len => Some(len),
+        }
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.duration
+    }
+}
+
+// smoke tests for the optional formats, on files put together by hand
+#[cfg(all(test, any(feature = "vorbis", feature = "aac", feature = "alac")))]
+mod tests {
+    use std::io::Cursor;
+
+    use rodio::Source;
+
+    use super::MediaDecoder;
+
+    // a bitstream, vorbis packs from the lowest bit, the others from the highest
+    struct Bits {
+        bytes: Vec<u8>,
+        used: usize,
+        low_first: bool,
+    }
+
+    impl Bits {
+        fn new(low_first: bool) -> Self {
+            Self {
+                bytes: vec![],
+                used: 0,
+                low_first,
+            }
+        }
+
+        fn put(&mut self, value: u32, len: usize) -> &mut Self {
+            for i in 0..len {
+                let bit = match self.low_first {
+                    true => value >> i & 1,
+                    false => value >> (len - 1 - i) & 1,
+                } as u8;
+                if self.used.is_multiple_of(8) {
+                    self.bytes.push(0);
+                }
+                let shift = match self.low_first {
+                    true => self.used % 8,
+                    false => 7 - self.used % 8,
+                };
+                *self.bytes.last_mut().unwrap() |= bit << shift;
+                self.used += 1;
+            }
+            self
+        }
+    }
+
+    fn decode(bytes: Vec<u8>, extension: &str) -> (u16, u32, Vec<i16>) {
+        let decoder = MediaDecoder::new(Box::new(Cursor::new(bytes)), Some(extension)).unwrap();
+        (decoder.channels(), decoder.sample_rate(), decoder.collect())
+    }
+
+    #[cfg(feature = "vorbis")]
+    fn ogg_page(out: &mut Vec<u8>, kind: u8, granule: u64, sequence: u32, packets: &[Vec<u8>]) {
+        let mut page = b"OggS\0".to_vec();
+        page.push(kind);
+        page.extend(granule.to_le_bytes());
+        page.extend(1u32.to_le_bytes());
+        page.extend(sequence.to_le_bytes());
+        page.extend([0; 4]);
+        let mut lacing = vec![];
+        for packet in packets {
+            lacing.extend(vec![255; packet.len() / 255]);
+            lacing.push((packet.len() % 255) as u8);
+        }
+        page.push(lacing.len() as u8);
+        page.extend(lacing);
+        packets.iter().for_each(|packet| page.extend(packet));
+        let crc = page.iter().fold(0u32, |crc, byte| {
+            (0..8).fold(crc ^ (*byte as u32) << 24, |crc, _| match crc >> 31 {
+                1 => crc << 1 ^ 0x04c1_1db7,
+                _ => crc << 1,
+            })
+        });
+        page[22..26].copy_from_slice(&crc.to_le_bytes());
+        out.extend(page);
+    }
+
+    // a mono stream of short blocks whose floors are all unused, so silence
+    #[cfg(feature = "vorbis")]
+    #[test]
+    fn decodes_vorbis() {
+        let mut ident = b"\x01vorbis".to_vec();
+        ident.extend(0u32.to_le_bytes());
+        ident.push(1);
+        ident.extend(44100u32.to_le_bytes());
+        ident.extend([0; 12]);
+        // 256 and 2048 sample blocks
+        ident.extend([0xb8, 1]);
+        let mut comment = b"\x03vorbis".to_vec();
+        comment.extend([0; 8]);
+        comment.push(1);
+        let mut setup = Bits::new(true);
+        // one codebook of two one bit entries
+        setup.put(0, 8).put(0x564342, 24).put(1, 16).put(2, 24);
+        setup.put(0, 2).put(0, 5).put(0, 5).put(0, 4);
+        // time domain transforms
+        setup.put(0, 6).put(0, 16);
+        // a type 1 floor without partitions
+        setup.put(0, 6).put(1, 16).put(0, 5).put(0, 2).put(7, 4);
+        // an empty type 1 residue
+        setup.put(0, 6).put(1, 16).put(0, 24).put(0, 24).put(0, 24);
+        setup.put(0, 6).put(0, 8).put(0, 3).put(0, 1);
+        // mapping and mode
+        setup.put(0, 6).put(0, 16).put(0, 4).put(0, 24);
+        setup.put(0, 6).put(0, 1).put(0, 32).put(0, 8);
+        setup.put(1, 1);
+        let mut setup_packet = b"\x05vorbis".to_vec();
+        setup_packet.extend(setup.bytes);
+
+        // every block after the first gives half a short block
+        let blocks = 100;
+        let mut file = vec![];
+        ogg_page(&mut file, 2, 0, 0, &[ident]);
+        ogg_page(&mut file, 0, 0, 1, &[comment, setup_packet]);
+        let audio = vec![vec![0]; blocks];
+        ogg_page(&mut file, 4, (blocks as u64 - 1) * 128, 2, &audio);
+
+        let (channels, rate, samples) = decode(file, "ogg");
+        assert_eq!((channels, rate), (1, 44100));
+        assert_eq!(samples.len(), (blocks - 1) * 128);
+        assert!(samples.iter().all(|sample| *sample == 0));
+    }
+
+    // adts frames of a mono channel without any bands, so silence
+    #[cfg(feature = "aac")]
+    #[test]
+    fn decodes_aac() {
+        let mut raw = Bits::new(false);
+        // single channel element, global gain, long window, no bands or tools
+        raw.put(0, 7).put(100, 8).put(0, 11).put(0, 3).put(7, 3);
+        let len = 7 + raw.bytes.len() as u32;
+        let mut header = Bits::new(false);
+        // mpeg-4, no crc, aac lc, 44.1 kHz, mono
+        header
+            .put(0xfff, 12)
+            .put(1, 4)
+            .put(1, 2)
+            .put(4, 4)
+            .put(0, 1)
+            .put(1, 3);
+        header.put(0, 4).put(len, 13).put(0x7ff, 11).put(0, 2);
+
+        let frames = 50;
+        let mut file = vec![];
+        for _ in 0..frames {
+            file.extend(&header.bytes);
+            file.extend(&raw.bytes);
+        }
+
+        let (channels, rate, samples) = decode(file, "aac");
+        assert_eq!((channels, rate), (1, 44100));
+        assert!(samples.len() >= (frames - 1) * 1024 && samples.len() <= frames * 1024);
+        assert!(samples.iter().all(|sample| *sample == 0));
+    }
+
+    #[cfg(feature = "alac")]
+    fn atom(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut atom = (8 + body.len() as u32).to_be_bytes().to_vec();
+        atom.extend(kind);
+        atom.extend(body);
+        atom
+    }
+
+    // version and flags first
+    #[cfg(feature = "alac")]
+    fn full_atom(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        atom(kind, &[&[0; 4], body].concat())
+    }
+
+    #[cfg(feature = "alac")]
+    fn be(values: &[u32]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect()
+    }
+
+    // an m4a of uncompressed alac frames, which must come back exactly
+    #[cfg(feature = "alac")]
+    #[test]
+    fn decodes_alac() {
+        let (frame_len, frames) = (1024, 8);
+        let tone: Vec<i16> = (0..frame_len * frames)
+            .map(|i| ((i as f32 * 0.05).sin() * 8000.0) as i16)
+            .collect();
+        let packets: Vec<Vec<u8>> = tone
+            .chunks(frame_len)
+            .map(|chunk| {
+                let mut frame = Bits::new(false);
+                // single channel element, whole frame, no shift, uncompressed
+                frame.put(0, 7).put(0, 12).put(0, 3).put(1, 1);
+                chunk.iter().for_each(|sample| {
+                    frame.put(*sample as u16 as u32, 16);
+                });
+                frame.put(7, 3);
+                frame.bytes
+            })
+            .collect();
+
+        let total = (frame_len * frames) as u32;
+        let mut cookie = be(&[frame_len as u32]);
+        // version, 16 bits, rice parameters, mono
+        cookie.extend([0, 16, 40, 10, 14, 1]);
+        cookie.extend([0, 255]);
+        cookie.extend(be(&[0, 0, 44100]));
+        let mut entry = vec![0; 6];
+        entry.extend([0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 16, 0, 0, 0, 0]);
+        entry.extend(be(&[44100 << 16]));
+        entry.extend(full_atom(b"alac", &cookie));
+        let sizes: Vec<u32> = packets.iter().map(|packet| packet.len() as u32).collect();
+        let matrix = be(&[0x10000, 0, 0, 0, 0x10000, 0, 0, 0, 0x4000_0000]);
+        let moov = |offset: u32| {
+            let stbl = [
+                full_atom(b"stsd", &[be(&[1]), atom(b"alac", &entry)].concat()),
+                full_atom(b"stts", &be(&[1, frames as u32, frame_len as u32])),
+                full_atom(b"stsc", &be(&[1, 1, frames as u32, 1])),
+                full_atom(b"stsz", &[be(&[0, frames as u32]), be(&sizes)].concat()),
+                full_atom(b"stco", &be(&[1, offset])),
+            ]
+            .concat();
+            let mdia = [
+                full_atom(
+                    b"mdhd",
+                    &[be(&[0, 0, 44100, total]), vec![0x55, 0xc4, 0, 0]].concat(),
+                ),
+                full_atom(b"hdlr", &[be(&[0]), b"soun".to_vec(), vec![0; 13]].concat()),
+                atom(b"minf", &atom(b"stbl", &stbl)),
+            ]
+            .concat();
+            let tkhd = [
+                be(&[0, 0, 1, 0, total, 0, 0, 0, 0]),
+                matrix.clone(),
+                be(&[0, 0]),
+            ];
+            let trak = [full_atom(b"tkhd", &tkhd.concat()), atom(b"mdia", &mdia)].concat();
+            let mvhd = [be(&[0, 0, 44100, total, 0x10000, 0x100 << 16, 0, 0])];
+            let mvhd = [&mvhd.concat(), &matrix, &[0; 24][..], &be(&[2])].concat();
+            atom(
+                b"moov",
+                &[full_atom(b"mvhd", &mvhd), atom(b"trak", &trak)].concat(),
+            )
+        };
+        let ftyp = atom(b"ftyp", b"M4A \0\0\0\0M4A isom");
+        let offset = (ftyp.len() + moov(0).len() + 8) as u32;
+        let file = [ftyp, moov(offset), atom(b"mdat", &packets.concat())].concat();
+
+        let (channels, rate, samples) = decode(file, "m4a");
+        assert_eq!((channels, rate), (1, 44100));
+        assert_eq!(samples, tone);
+    }
+}
@@ -0,0 +1,66 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use rodio::{
+    cpal::{self, traits::DeviceTrait, traits::HostTrait},
+    Device, OutputStream, OutputStreamHandle,
+};
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct OutputDevice {
+    pub host: String,
+    pub name: String,
+}
+
+/// every output device of every available host
+pub fn output_devices() -> Vec<OutputDevice> {
+    let mut devices = vec![];
+    for id in cpal::available_hosts() {
+        let host = match cpal::host_from_id(id) {
+            Ok(host) => host,
+            Err(_) => continue,
+        };
+        if let Ok(outputs) = host.output_devices() {
+            for device in outputs {
+                if let Ok(name) = device.name() {
+                    devices.push(OutputDevice {
+                        host: id.name().to_string(),
+                        name,
+                    });
+                }
+            }
+        }
+    }
+    devices
+}
+
+fn find(device: &OutputDevice) -> Option<Device> {
+    let id = cpal::available_hosts()
+        .into_iter()
+        .find(|id| id.name() == device.host)?;
+    let host = cpal::host_from_id(id).ok()?;
+    let mut outputs = host.output_devices().ok()?;
+    outputs.find(|d| d.name().map(|name| name == device.name).unwrap_or(false))
+}
+
+/// open a stream on `device`, or on the default device when `None`
+pub fn open(device: Option<&OutputDevice>) -> Option<(OutputStream, OutputStreamHandle)> {
+    match device {
+        Some(device) => OutputStream::try_from_device(&find(device)?).ok(),
+        None => OutputStream::try_default().ok(),
+    }
+}
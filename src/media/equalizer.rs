@@ -0,0 +1,325 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    f32::consts::PI,
+    mem,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use rodio::Source;
+
+pub const BANDS: usize = 10;
+// ISO octave centres
+pub const FREQUENCIES: [f32; BANDS] = [
+    31.0, 62.0, 125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0, 16000.0,
+];
+pub const MAX_GAIN: f32 = 12.0;
+// one octave wide
+const Q: f32 = 1.41;
+
+pub const PRESETS: [(&str, [f32; BANDS]); 6] = [
+    ("Flat", [0.0; BANDS]),
+    ("Bass", [6.0, 5.0, 4.0, 2.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]),
+    ("Treble", [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 2.0, 4.0, 5.0, 6.0]),
+    (
+        "Vocal",
+        [-2.0, -2.0, -1.0, 1.0, 3.0, 4.0, 3.0, 1.0, 0.0, -1.0],
+    ),
+    ("Rock", [4.0, 3.0, 2.0, 0.0, -1.0, -1.0, 1.0, 3.0, 4.0, 4.0]),
+    (
+        "Classical",
+        [3.0, 2.0, 1.0, 0.0, 0.0, 0.0, -1.0, -1.0, 1.0, 2.0],
+    ),
+];
+
+/// Band gains in dB shared with every [`Equalizer`] in the chain.
+#[derive(Clone)]
+pub struct EqControl {
+    inner: Arc<EqInner>,
+}
+
+struct EqInner {
+    gains: Mutex<[f32; BANDS]>,
+    enabled: AtomicBool,
+    // bumped on every change so the filters are rebuilt
+    generation: AtomicU32,
+}
+
+impl EqControl {
+    pub fn new(gains: [f32; BANDS], enabled: bool) -> Self {
+        Self {
+            inner: Arc::new(EqInner {
+                gains: Mutex::new(gains),
+                enabled: AtomicBool::new(enabled),
+                generation: AtomicU32::new(0),
+            }),
+        }
+    }
+
+    pub fn gains(&self) -> [f32; BANDS] {
+        *self.inner.gains.lock().unwrap()
+    }
+
+    pub fn set_gains(&self, gains: [f32; BANDS]) {
+        *self.inner.gains.lock().unwrap() = gains.map(|gain| gain.clamp(-MAX_GAIN, MAX_GAIN));
+        self.inner.generation.fetch_add(1, Ordering::Release);
+    }
+
+    pub fn adjust(&self, band: usize, inc: f32) {
+        let mut gains = self.gains();
+        gains[band] += inc;
+        self.set_gains(gains);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.inner.enabled.store(enabled, Ordering::Relaxed);
+        self.inner.generation.fetch_add(1, Ordering::Release);
+    }
+}
+
+// RBJ peaking filter in transposed direct form II
+#[derive(Clone, Copy)]
+struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+}
+
+impl Biquad {
+    fn peaking(freq: f32, gain: f32, sample_rate: u32) -> Self {
+        let a = 10f32.powf(gain / 40.0);
+        let w0 = 2.0 * PI * freq / sample_rate as f32;
+        let alpha = w0.sin() / (2.0 * Q);
+        let cos = w0.cos();
+        let a0 = 1.0 + alpha / a;
+        Self {
+            b0: (1.0 + alpha * a) / a0,
+            b1: -2.0 * cos / a0,
+            b2: (1.0 - alpha * a) / a0,
+            a1: -2.0 * cos / a0,
+            a2: (1.0 - alpha / a) / a0,
+        }
+    }
+}
+
+/// Graphic equalizer: one peaking filter per band that is not flat.
+pub struct Equalizer<I> {
+    input: I,
+    control: EqControl,
+    generation: u32,
+    filters: Vec<Biquad>,
+    // band of each filter
+    bands: Vec<usize>,
+    // z1, z2 of every filter for every channel
+    state: Vec<[f32; 2]>,
+    // keeps the loudest boost from clipping
+    preamp: f32,
+    channel: usize,
+}
+
+impl<I> Equalizer<I>
+where
+    I: Source<Item = f32>,
+{
+    pub fn new(input: I, control: EqControl) -> Self {
+        Self {
+            input,
+            control,
+            // build the filters on the first sample
+            generation: u32::MAX,
+            filters: vec![],
+            bands: vec![],
+            state: vec![],
+            preamp: 1.0,
+            channel: 0,
+        }
+    }
+
+    fn update(&mut self) {
+        let generation = self.control.inner.generation.load(Ordering::Acquire);
+        if generation == self.generation {
+            return;
+        }
+        self.generation = generation;
+        let old = mem::take(&mut self.bands);
+        self.filters.clear();
+        self.preamp = 1.0;
+        if self.control.is_enabled() {
+            let gains = self.control.gains();
+            let nyquist = self.input.sample_rate() as f32 / 2.0;
+            for (band, (freq, gain)) in FREQUENCIES.iter().zip(gains).enumerate() {
+                // bands above nyquist would alias
+                if gain != 0.0 && *freq < nyquist * 0.9 {
+                    self.filters
+                        .push(Biquad::peaking(*freq, gain, self.input.sample_rate()));
+                    self.bands.push(band);
+                }
+            }
+            let boost = gains.iter().cloned().fold(0.0, f32::max);
+            self.preamp = 10f32.powf(-boost / 20.0);
+        }
+        // the filter memory only carries over while the same bands are filtered
+        let len = self.filters.len() * self.input.channels().max(1) as usize;
+        if self.bands != old || self.state.len() != len {
+            self.state = vec![[0.0; 2]; len];
+        }
+    }
+}
+
+impl<I> Iterator for Equalizer<I>
+where
+    I: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.channel == 0 {
+            self.update();
+        }
+        let mut sample = self.input.next()?;
+        let channels = self.input.channels().max(1) as usize;
+        if !self.filters.is_empty() {
+            sample *= self.preamp;
+            for (i, f) in self.filters.iter().enumerate() {
+                let z = &mut self.state[i * channels + self.channel];
+                let out = f.b0 * sample + z[0];
+                z[0] = f.b1 * sample - f.a1 * out + z[1];
+                z[1] = f.b2 * sample - f.a2 * out;
+                sample = out;
+            }
+        }
+        self.channel = (self.channel + 1) % channels;
+        Some(sample)
+    }
+}
+
+impl<I> Source for Equalizer<I>
+where
+    I: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use rodio::buffer::SamplesBuffer;
+
+    use super::{Biquad, EqControl, Equalizer, BANDS, FREQUENCIES, MAX_GAIN, PRESETS};
+
+    const RATE: u32 = 44100;
+
+    // gain in dB of the filter at `freq`
+    fn response(f: &Biquad, freq: f32) -> f32 {
+        let w = 2.0 * PI * freq / RATE as f32;
+        // numerator and denominator at z = e^jw, as (re, im)
+        let at = |c0: f32, c1: f32, c2: f32| {
+            (
+                c0 + c1 * w.cos() + c2 * (2.0 * w).cos(),
+                -c1 * w.sin() - c2 * (2.0 * w).sin(),
+            )
+        };
+        let (nr, ni) = at(f.b0, f.b1, f.b2);
+        let (dr, di) = at(1.0, f.a1, f.a2);
+        10.0 * ((nr * nr + ni * ni) / (dr * dr + di * di)).log10()
+    }
+
+    #[test]
+    fn peaks_at_the_band() {
+        for freq in FREQUENCIES.iter().take(9) {
+            for gain in [-MAX_GAIN, -3.0, 6.0, MAX_GAIN] {
+                let f = Biquad::peaking(*freq, gain, RATE);
+                assert!((response(&f, *freq) - gain).abs() < 0.05, "{} Hz", freq);
+                // two octaves away there is little left of it
+                assert!(response(&f, freq / 4.0).abs() < gain.abs() / 4.0);
+            }
+        }
+        // flat is no filter at all
+        let f = Biquad::peaking(1000.0, 0.0, RATE);
+        assert!((f.b0 - 1.0).abs() < 1e-6 && f.b1 == f.a1 && (f.b2 - f.a2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn presets_are_in_range() {
+        assert_eq!(PRESETS[0], ("Flat", [0.0; BANDS]));
+        for (i, (name, gains)) in PRESETS.iter().enumerate() {
+            assert!(gains.iter().all(|gain| gain.abs() <= MAX_GAIN), "{}", name);
+            assert!(PRESETS[..i].iter().all(|(other, _)| other != name));
+        }
+    }
+
+    fn noise(len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| ((i * 7919 % 1000) as f32 / 500.0 - 1.0) * 0.5)
+            .collect()
+    }
+
+    #[test]
+    fn flat_passes_through() {
+        let input = noise(4000);
+        let eq = EqControl::new([0.0; BANDS], true);
+        let output: Vec<f32> =
+            Equalizer::new(SamplesBuffer::new(2, RATE, input.clone()), eq).collect();
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn moved_band_starts_from_silence() {
+        let input = noise(4000);
+        let mut gains = [0.0; BANDS];
+        gains[3] = 6.0;
+        let eq = EqControl::new(gains, true);
+        let mut moved = Equalizer::new(SamplesBuffer::new(2, RATE, input.clone()), eq.clone());
+        moved.by_ref().take(2000).for_each(drop);
+        // as many bands as before, but another one
+        gains[3] = 0.0;
+        gains[5] = 6.0;
+        eq.set_gains(gains);
+        let moved: Vec<f32> = moved.collect();
+
+        let fresh = EqControl::new(gains, true);
+        let fresh: Vec<f32> =
+            Equalizer::new(SamplesBuffer::new(2, RATE, input[2000..].to_vec()), fresh).collect();
+        assert_eq!(moved, fresh);
+    }
+}
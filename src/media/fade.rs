@@ -0,0 +1,175 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use rodio::Source;
+
+/// Gain ramp requested by the player and applied by a [`Fade`] in the chain.
+#[derive(Clone)]
+pub struct FadeControl {
+    inner: Arc<FadeInner>,
+}
+
+struct FadeInner {
+    target: AtomicU32,
+    ramp_nanos: AtomicU64,
+    // bumped on every request so the source picks it up
+    generation: AtomicU32,
+    // gain the source is applying right now
+    gain: AtomicU32,
+}
+
+impl FadeControl {
+    pub fn new(gain: f32) -> Self {
+        Self {
+            inner: Arc::new(FadeInner {
+                target: AtomicU32::new(gain.to_bits()),
+                ramp_nanos: AtomicU64::new(0),
+                generation: AtomicU32::new(0),
+                gain: AtomicU32::new(gain.to_bits()),
+            }),
+        }
+    }
+
+    /// ramp linearly from the current gain to `target` over `over`
+    pub fn fade_to(&self, target: f32, over: Duration) {
+        let inner = &self.inner;
+        inner.target.store(target.to_bits(), Ordering::Relaxed);
+        inner
+            .ramp_nanos
+            .store(over.as_nanos() as u64, Ordering::Relaxed);
+        inner.generation.fetch_add(1, Ordering::Release);
+    }
+
+    pub fn gain(&self) -> f32 {
+        f32::from_bits(self.inner.gain.load(Ordering::Relaxed))
+    }
+
+    pub fn target(&self) -> f32 {
+        f32::from_bits(self.inner.target.load(Ordering::Relaxed))
+    }
+
+    /// the ramp has reached its target
+    pub fn is_settled(&self) -> bool {
+        self.gain() == self.target()
+    }
+}
+
+/// Applies the gain ramp of a [`FadeControl`], sample by sample.
+pub struct Fade<I> {
+    input: I,
+    control: FadeControl,
+    generation: u32,
+    gain: f32,
+    target: f32,
+    step: f32,
+}
+
+impl<I> Fade<I>
+where
+    I: Source<Item = f32>,
+{
+    pub fn new(input: I, control: FadeControl) -> Self {
+        let gain = control.gain();
+        Self {
+            input,
+            control,
+            // pick up whatever was requested before we were built
+            generation: u32::MAX,
+            gain,
+            target: gain,
+            step: 0.0,
+        }
+    }
+
+    fn update(&mut self) {
+        let inner = &self.control.inner;
+        let generation = inner.generation.load(Ordering::Acquire);
+        if generation == self.generation {
+            return;
+        }
+        self.generation = generation;
+        self.target = f32::from_bits(inner.target.load(Ordering::Relaxed));
+        let ramp = inner.ramp_nanos.load(Ordering::Relaxed) as f64 / 1e9;
+        let samples = ramp * self.input.sample_rate() as f64 * self.input.channels() as f64;
+        if samples < 1.0 {
+            self.gain = self.target;
+            self.step = 0.0;
+            self.publish();
+        } else {
+            self.step = ((self.target - self.gain) as f64 / samples) as f32;
+        }
+    }
+
+    fn publish(&self) {
+        self.control
+            .inner
+            .gain
+            .store(self.gain.to_bits(), Ordering::Relaxed);
+    }
+}
+
+impl<I> Iterator for Fade<I>
+where
+    I: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        self.update();
+        let sample = self.input.next()? * self.gain;
+        if self.step != 0.0 {
+            self.gain += self.step;
+            if (self.step > 0.0 && self.gain >= self.target)
+                || (self.step < 0.0 && self.gain <= self.target)
+            {
+                self.gain = self.target;
+                self.step = 0.0;
+            }
+            self.publish();
+        }
+        Some(sample)
+    }
+}
+
+impl<I> Source for Fade<I>
+where
+    I: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
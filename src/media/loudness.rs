@@ -0,0 +1,257 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    fs::File,
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, Sender},
+};
+
+use ebur128::{EbuR128, Mode};
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+use symphonia::core::{
+    formats::FormatOptions,
+    io::MediaSourceStream,
+    meta::{MetadataOptions, MetadataRevision, StandardTagKey},
+    probe::Hint,
+};
+
+use super::{
+    cache::{FileCache, Priority, Worker},
+    decoder,
+};
+
+// ReplayGain 2.0 reference loudness
+const REFERENCE_LUFS: f64 = -18.0;
+// frames handed to the meter at once
+const CHUNK_FRAMES: usize = 4096;
+
+/// Which ReplayGain value is applied.
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum GainMode {
+    Off,
+    Track,
+    // album gain where tagged, track gain otherwise
+    Album,
+}
+
+pub struct ReplayGain {
+    pub mode: GainMode,
+    // dB added on top of the gain
+    pub preamp: f32,
+    // lower the gain so the peak stays below full scale
+    pub clip_protection: bool,
+}
+
+impl Default for ReplayGain {
+    fn default() -> Self {
+        Self {
+            mode: GainMode::Track,
+            preamp: 0.0,
+            clip_protection: true,
+        }
+    }
+}
+
+impl ReplayGain {
+    /// linear gain for a file with the given tags and measurement
+    pub fn linear(&self, tags: &GainTags, measured: Option<(f64, f32)>) -> f32 {
+        let (gain, peak) = match self.mode {
+            GainMode::Off => return 1.0,
+            GainMode::Track => (tags.track_gain, tags.track_peak),
+            GainMode::Album => (
+                tags.album_gain.or(tags.track_gain),
+                tags.album_peak.or(tags.track_peak),
+            ),
+        };
+        let gain = match (gain, measured) {
+            (Some(gain), _) => gain,
+            (None, Some((loudness, _))) => (REFERENCE_LUFS - loudness) as f32,
+            // still being measured
+            (None, None) => return 1.0,
+        };
+        let mut linear = 10f32.powf((gain + self.preamp) / 20.0);
+        if self.clip_protection {
+            if let Some(peak) = peak.or(measured.map(|(_, peak)| peak)) {
+                if peak > 0.0 {
+                    linear = linear.min(1.0 / peak);
+                }
+            }
+        }
+        linear
+    }
+}
+
+/// ReplayGain tags of a file, gains in dB and peaks as linear amplitude.
+#[derive(Clone, Copy, Default)]
+pub struct GainTags {
+    pub track_gain: Option<f32>,
+    pub track_peak: Option<f32>,
+    pub album_gain: Option<f32>,
+    pub album_peak: Option<f32>,
+}
+
+impl GainTags {
+    pub fn read(path: &str) -> Self {
+        let mut tags = Self::default();
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(_) => return tags,
+        };
+        let mss = MediaSourceStream::new(Box::new(file), Default::default());
+        let mut hint = Hint::new();
+        if let Some(ext) = PathBuf::from(path).extension() {
+            hint.with_extension(&ext.to_string_lossy());
+        }
+        let mut probed = match symphonia::default::get_probe().format(
+            &hint,
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        ) {
+            Ok(probed) => probed,
+            Err(_) => return tags,
+        };
+        // tags in front of the container (id3v2) and inside it (vorbis comments)
+        if let Some(metadata) = probed.metadata.get() {
+            if let Some(revision) = metadata.current() {
+                tags.merge(revision);
+            }
+        }
+        if let Some(revision) = probed.format.metadata().current() {
+            tags.merge(revision);
+        }
+        tags
+    }
+
+    fn merge(&mut self, revision: &MetadataRevision) {
+        for tag in revision.tags() {
+            let key = tag.key.to_uppercase();
+            let slot = match tag.std_key {
+                Some(StandardTagKey::ReplayGainTrackGain) => &mut self.track_gain,
+                Some(StandardTagKey::ReplayGainTrackPeak) => &mut self.track_peak,
+                Some(StandardTagKey::ReplayGainAlbumGain) => &mut self.album_gain,
+                Some(StandardTagKey::ReplayGainAlbumPeak) => &mut self.album_peak,
+                // id3v2 keeps them in TXXX frames
+                _ if key.ends_with("REPLAYGAIN_TRACK_GAIN") => &mut self.track_gain,
+                _ if key.ends_with("REPLAYGAIN_TRACK_PEAK") => &mut self.track_peak,
+                _ if key.ends_with("REPLAYGAIN_ALBUM_GAIN") => &mut self.album_gain,
+                _ if key.ends_with("REPLAYGAIN_ALBUM_PEAK") => &mut self.album_peak,
+                _ => continue,
+            };
+            // "-6.48 dB"
+            let value = tag.value.to_string();
+            if let Some(value) = value.split_whitespace().next() {
+                if let Ok(value) = value.parse::<f32>() {
+                    *slot = Some(value);
+                }
+            }
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.track_gain.is_some() && self.track_peak.is_some()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Measurement {
+    // integrated loudness in LUFS
+    loudness: f64,
+    peak: f32,
+}
+
+/// EBU R128 measurements of untagged files, made on the [`Worker`] and
+/// kept in `<cache dir>/rustplayer/loudness.toml`.
+pub struct Loudness {
+    cache: FileCache<Measurement>,
+    worker: Worker,
+    sender: Sender<String>,
+    results: Receiver<String>,
+}
+
+impl Loudness {
+    pub fn new(worker: &Worker) -> Self {
+        let (sender, results) = mpsc::channel();
+        Self {
+            cache: FileCache::new("loudness", worker),
+            worker: worker.clone(),
+            sender,
+            results,
+        }
+    }
+
+    /// queue `path` for measuring unless the cache has it already
+    pub fn request(&self, path: &str) {
+        if self.cache.get(path).is_some() {
+            return;
+        }
+        let (cache, sender, path) = (self.cache.clone(), self.sender.clone(), path.to_string());
+        // a whole file to decode, the song plays at unity gain until then
+        self.worker.run(Priority::Low, move || {
+            if let Some((loudness, peak)) = measure(path.as_str()) {
+                cache.insert(path.as_str(), Measurement { loudness, peak });
+            }
+            let _ = sender.send(path);
+        });
+    }
+
+    /// loudness and peak of `path`, None until it has been measured
+    pub fn get(&self, path: &str) -> Option<(f64, f32)> {
+        self.cache
+            .get(path)
+            .map(|entry| (entry.loudness, entry.peak))
+    }
+
+    /// path of a file measured since the last call
+    pub fn poll(&self) -> Option<String> {
+        self.results.try_recv().ok()
+    }
+}
+
+// integrated loudness and sample peak of the whole file
+fn measure(path: &str) -> Option<(f64, f32)> {
+    let decoder = decoder::open(path).ok()?;
+    let channels = decoder.channels().max(1) as usize;
+    let mut meter = EbuR128::new(
+        channels as u32,
+        decoder.sample_rate(),
+        Mode::I | Mode::SAMPLE_PEAK,
+    )
+    .ok()?;
+    let mut buf = Vec::with_capacity(CHUNK_FRAMES * channels);
+    for sample in decoder {
+        buf.push(sample);
+        if buf.len() == CHUNK_FRAMES * channels {
+            meter.add_frames_i16(&buf).ok()?;
+            buf.clear();
+        }
+    }
+    let whole = buf.len() / channels * channels;
+    meter.add_frames_i16(&buf[..whole]).ok()?;
+    let loudness = meter.loudness_global().ok()?;
+    if !loudness.is_finite() {
+        // silence
+        return None;
+    }
+    let mut peak = 0.0f64;
+    for channel in 0..channels {
+        peak = peak.max(meter.sample_peak(channel as u32).ok()?);
+    }
+    Some((loudness, peak as f32))
+}
@@ -18,6 +18,8 @@
 
 pub enum Source {
     Local(String),
+    // internet radio, http(s) url
+    Http(String),
 }
 
 pub struct Media {
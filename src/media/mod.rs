@@ -15,5 +15,22 @@
 // You should have received a copy of the GNU General Public License
 // along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
 
+pub mod cache;
+pub mod channels;
+pub mod decoder;
+pub mod device;
+pub mod equalizer;
+pub mod fade;
+pub mod loudness;
+#[allow(clippy::module_inception)]
 pub mod media;
+#[cfg(feature = "opus")]
+pub mod opus;
+pub mod output;
 pub mod player;
+pub mod playlist;
+pub mod probe;
+pub mod segment;
+pub mod stream;
+pub mod stretch;
+pub mod tracker;
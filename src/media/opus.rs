@@ -0,0 +1,66 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use audiopus::{coder::Decoder, packet::Packet, Channels, MutSignals, SampleRate};
+use symphonia::core::errors::Error;
+
+// opus always decodes at 48 kHz
+const RATE: u32 = 48000;
+// longest frame, 120 ms
+const MAX_FRAMES: usize = 5760;
+
+/// Opus packets demuxed from Ogg by symphonia, decoded with libopus.
+pub struct OpusCodec {
+    decoder: Decoder,
+    layout: Channels,
+    channels: usize,
+}
+
+impl OpusCodec {
+    pub fn new(channels: usize) -> Option<Self> {
+        let layout = match channels {
+            1 => Channels::Mono,
+            2 => Channels::Stereo,
+            _ => return None,
+        };
+        Some(Self {
+            decoder: Decoder::new(SampleRate::Hz48000, layout).ok()?,
+            layout,
+            channels,
+        })
+    }
+
+    pub fn decode(&mut self, data: &[u8], out: &mut Vec<i16>) -> Result<(u16, u32), Error> {
+        let bad = |_| Error::DecodeError("bad opus packet");
+        out.resize(MAX_FRAMES * self.channels, 0);
+        let packet = Packet::try_from(data).map_err(bad)?;
+        let signals = MutSignals::try_from(out.as_mut_slice()).map_err(bad)?;
+        let frames = self
+            .decoder
+            .decode(Some(packet), signals, false)
+            .map_err(bad)?;
+        out.truncate(frames * self.channels);
+        Ok((self.channels as u16, RATE))
+    }
+
+    // after a seek, the state of the old position must not leak into the new one
+    pub fn reset(&mut self) {
+        if let Ok(decoder) = Decoder::new(SampleRate::Hz48000, self.layout) {
+            self.decoder = decoder;
+        }
+    }
+}
@@ -0,0 +1,237 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    fmt,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use failure::{Error, Fail};
+use hound::{SampleFormat, WavSpec, WavWriter};
+use rodio::{
+    dynamic_mixer::{self, DynamicMixerController},
+    OutputStream, OutputStreamHandle, Sink,
+};
+
+use super::device::{self, OutputDevice};
+
+const CHANNELS: u16 = 2;
+const SAMPLE_RATE: u32 = 44100;
+// audio pulled per round by the mixer backends
+const CHUNK: Duration = Duration::from_millis(10);
+
+/// Where the sinks of the player end up.
+pub trait Output {
+    // a new sink playing on this output
+    fn new_sink(&self) -> Sink;
+
+    fn name(&self) -> String;
+}
+
+#[derive(Debug)]
+pub struct OutputError {
+    msg: String,
+}
+
+impl fmt::Display for OutputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "OutputError: {}", self.msg)
+    }
+}
+
+impl Fail for OutputError {}
+
+/// Output selected by `--output` or the `output` config key:
+/// `device`, `null`, `null:<speed factor>` or `wav:<path>`.
+#[derive(Clone, PartialEq)]
+pub enum OutputKind {
+    Device,
+    Null(f32),
+    Wav(String),
+}
+
+impl FromStr for OutputKind {
+    type Err = OutputError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, arg) = match s.split_once(':') {
+            Some((kind, arg)) => (kind, Some(arg)),
+            None => (s, None),
+        };
+        match (kind, arg) {
+            ("device", None) => Ok(OutputKind::Device),
+            ("null", None) => Ok(OutputKind::Null(1.0)),
+            ("null", Some(factor)) => match factor.parse::<f32>() {
+                Ok(factor) if factor > 0.0 => Ok(OutputKind::Null(factor)),
+                _ => Err(OutputError {
+                    msg: format!("bad speed factor: {}", factor),
+                }),
+            },
+            ("wav", Some(path)) if !path.is_empty() => Ok(OutputKind::Wav(path.to_string())),
+            _ => Err(OutputError {
+                msg: format!("unknown output: {}", s),
+            }),
+        }
+    }
+}
+
+/// open the output, `device` only matters for [`OutputKind::Device`]
+pub fn open(kind: &OutputKind, device: Option<&OutputDevice>) -> Result<Box<dyn Output>, Error> {
+    match kind {
+        OutputKind::Device => Ok(Box::new(DeviceOutput::open(device)?)),
+        OutputKind::Null(factor) => Ok(Box::new(null_output(*factor))),
+        OutputKind::Wav(path) => {
+            let spec = WavSpec {
+                channels: CHANNELS,
+                sample_rate: SAMPLE_RATE,
+                bits_per_sample: 16,
+                sample_format: SampleFormat::Int,
+            };
+            let mut writer = WavWriter::create(path, spec)?;
+            let mut written = 0;
+            Ok(Box::new(MixerOutput::spawn(
+                format!("wav ({})", path),
+                1.0,
+                move |samples| {
+                    for sample in samples {
+                        let sample = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+                        let _ = writer.write_sample(sample);
+                    }
+                    // keep the header valid in case we never get to finalize
+                    written += samples.len();
+                    if written >= (SAMPLE_RATE * CHANNELS as u32) as usize {
+                        written = 0;
+                        let _ = writer.flush();
+                    }
+                },
+            )))
+        }
+    }
+}
+
+/// discards everything, for machines without a sound card
+pub fn null_output(factor: f32) -> MixerOutput {
+    MixerOutput::spawn(format!("null ({}x)", factor), factor, |_| {})
+}
+
+/// A real sound card, through cpal.
+pub struct DeviceOutput {
+    // dropping the stream closes the device
+    _stream: OutputStream,
+    handle: OutputStreamHandle,
+    name: String,
+}
+
+impl DeviceOutput {
+    pub fn open(device: Option<&OutputDevice>) -> Result<Self, OutputError> {
+        let name = match device {
+            Some(device) => format!("{}: {}", device.host, device.name),
+            None => String::from("default device"),
+        };
+        match device::open(device) {
+            Some((stream, handle)) => Ok(Self {
+                _stream: stream,
+                handle,
+                name,
+            }),
+            None => Err(OutputError {
+                msg: format!("can not open {}", name),
+            }),
+        }
+    }
+}
+
+impl Output for DeviceOutput {
+    fn new_sink(&self) -> Sink {
+        // a sink that plays nowhere beats a panic
+        Sink::try_new(&self.handle).unwrap_or_else(|_| Sink::new_idle().0)
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+}
+
+/// Mixes every sink on its own thread and hands the samples to `write`,
+/// `factor` times faster than real time.
+pub struct MixerOutput {
+    controller: Arc<DynamicMixerController<f32>>,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+    name: String,
+}
+
+impl MixerOutput {
+    pub fn spawn<F>(name: String, factor: f32, mut write: F) -> Self
+    where
+        F: FnMut(&[f32]) + Send + 'static,
+    {
+        let (controller, mut mixer) = dynamic_mixer::mixer::<f32>(CHANNELS, SAMPLE_RATE);
+        let running = Arc::new(AtomicBool::new(true));
+        let flag = running.clone();
+        let thread = thread::spawn(move || {
+            let len = (SAMPLE_RATE as u128 * CHUNK.as_millis() / 1000) as usize * CHANNELS as usize;
+            let period = CHUNK.div_f32(factor);
+            let mut buf = Vec::with_capacity(len);
+            let mut deadline = Instant::now();
+            while flag.load(Ordering::Relaxed) {
+                buf.clear();
+                // nothing playing is silence
+                buf.extend((0..len).map(|_| mixer.next().unwrap_or(0.0)));
+                write(&buf);
+                deadline += period;
+                if let Some(wait) = deadline.checked_duration_since(Instant::now()) {
+                    thread::sleep(wait);
+                }
+            }
+        });
+        Self {
+            controller,
+            running,
+            thread: Some(thread),
+            name,
+        }
+    }
+}
+
+impl Output for MixerOutput {
+    fn new_sink(&self) -> Sink {
+        let (sink, queue) = Sink::new_idle();
+        self.controller.add(queue);
+        sink
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+}
+
+impl Drop for MixerOutput {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            // the writer is finalized when the thread drops it
+            let _ = thread.join();
+        }
+    }
+}
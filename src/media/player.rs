@@ -16,63 +16,204 @@
 // along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::{
-    fs::File,
-    io::BufReader,
+    collections::HashMap,
+    fmt, fs, mem,
     ops::Add,
     path::Path,
+    thread,
     time::{Duration, Instant},
 };
 
-use rodio::cpal;
-use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source};
-use tui::widgets::ListState;
+use chrono::{DateTime, Local};
+use failure::Fail;
+use rand::{seq::SliceRandom, thread_rng, Rng};
+use rodio::{source::Zero, Sink, Source};
 
-use super::media::Media;
+use super::{
+    cache::Worker,
+    channels::{ChannelControl, ChannelMixer},
+    decoder::{self, Track},
+    device::OutputDevice,
+    equalizer::{EqControl, Equalizer, BANDS},
+    fade::{Fade, FadeControl},
+    loudness::{GainTags, Loudness, ReplayGain},
+    media::Media,
+    output::{DeviceOutput, Output, OutputError},
+    playlist,
+    probe::Prober,
+    segment::{SegmentSettings, Segmenter, Segments},
+    stream::{connect, is_url, Connecting, StreamMeta},
+    stretch::{SpeedControl, TimeStretch},
+    tracker::{Cancel, Cancellable, Done, Position, Tracked},
+};
+
+// how early the next pass is appended to the sink
+const PRELOAD_AHEAD: Duration = Duration::from_secs(2);
+// past this, previous restarts the current song instead
+const RESTART_AFTER: Duration = Duration::from_secs(3);
+// how long to wait for a ramp that may never run, e.g. during a gap
+const RAMP_MARGIN: Duration = Duration::from_millis(50);
+// loudness changes smaller than this are not worth a ramp
+const GAIN_EPSILON: f32 = 0.01;
+const GAIN_GLIDE: Duration = Duration::from_secs(1);
+// seconds, a third of the song at most is overlapped whatever the setting
+const MIN_CROSSFADE: f32 = 0.5;
+const MAX_CROSSFADE: f32 = 12.0;
+// samples an idle sink converts with the channels of its mono placeholder
+const LEAD_IN: usize = 512;
 
 #[derive(PartialEq, Eq, PartialOrd, Ord)]
 pub enum PlayStatus {
     Waiting,
-    Playing(Instant, Duration),
-    // elapsed, times already played
-    Stopped(Duration), // times already played
+    Playing,
+    Stopped,
+}
+
+#[derive(Debug)]
+pub enum PlayerError {
+    FileMissing(String),
+    UnsupportedFormat(String),
+    Decode(String, String),
+    Stream(String, String),
+    NotSeekable,
+    Write(String, String),
+    Device(OutputError),
+    EmptyQueue,
+    NoSuchItem,
+    NoLoopStart,
+}
+
+impl fmt::Display for PlayerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlayerError::FileMissing(path) => write!(f, "File not found: {}", path),
+            PlayerError::UnsupportedFormat(path) => write!(f, "Unsupported format: {}", path),
+            PlayerError::Decode(path, err) => write!(f, "Decode failed: {}: {}", path, err),
+            PlayerError::Stream(url, err) => write!(f, "Can not open stream {}: {}", url, err),
+            PlayerError::NotSeekable => write!(f, "Streams can not seek"),
+            PlayerError::Write(path, err) => write!(f, "Can not write {}: {}", path, err),
+            PlayerError::Device(err) => write!(f, "{}", err),
+            PlayerError::EmptyQueue => write!(f, "Play list is empty"),
+            PlayerError::NoSuchItem => write!(f, "No such item in the play list"),
+            PlayerError::NoLoopStart => write!(f, "Set loop start (a) before loop end (b)"),
+        }
+    }
+}
+
+impl Fail for PlayerError {
+    fn cause(&self) -> Option<&dyn Fail> {
+        match self {
+            PlayerError::Device(err) => Some(err),
+            _ => None,
+        }
+    }
 }
 
 pub struct PlayListItem {
     pub name: String,
-    pub duration: Duration,
-    pub current_pos: Duration,
+    // None until the prober has found it, or when it can not be found
+    pub duration: Option<Duration>,
     pub status: PlayStatus,
     pub path: String,
     pub repetition: i32,
+    // seconds of silence after each pass
+    pub gap: f32,
+    // None until the file has been read
+    pub gain_tags: Option<GainTags>,
+    // start and end in the file when this is a segment of it,
+    // `duration` is then the length of the segment
+    pub segment: Option<(Duration, Duration)>,
+    // titles of a station, shared by every item of the same url
+    pub meta: Option<StreamMeta>,
+}
+
+impl PlayListItem {
+    pub fn is_stream(&self) -> bool {
+        is_url(self.path.as_str())
+    }
 }
 
 pub struct PlayList {
     pub lists: Vec<PlayListItem>,
+    // index of the song being played, played songs stay in `lists`
+    pub current: Option<usize>,
 }
 
-pub trait Player {
-    // 初始化
-    fn new() -> Self;
+impl PlayList {
+    pub fn playing(&self) -> Option<&PlayListItem> {
+        self.lists.get(self.current?)
+    }
+
+    fn playing_mut(&mut self) -> Option<&mut PlayListItem> {
+        self.lists.get_mut(self.current?)
+    }
+}
+
+// a song that started playing in this session
+pub struct HistoryEntry {
+    pub name: String,
+    pub played_at: DateTime<Local>,
+}
 
+// what plays once a song is over
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum QueueMode {
+    // stop after the last song
+    Off,
+    RepeatOne,
+    RepeatAll,
+    // every song once, in random order
+    Shuffle,
+}
+
+// what happens between two passes
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum Transition {
+    // pause for `gap` seconds
+    Gap,
+    // overlap the passes for `crossfade` seconds
+    Crossfade,
+}
+
+// how long the gap after a pass is
+#[derive(PartialEq, Eq, Clone, Copy)]
+pub enum GapMode {
+    // the gap of the song, in seconds
+    Fixed,
+    // long enough to repeat the pass aloud: factor × pass length + the gap of the song
+    Shadowing,
+}
+
+pub struct AbLoop {
+    pub a: Duration,
+    pub b: Option<Duration>,
+    // passes left before playing on past b
+    pub remaining: i32,
+}
+
+pub trait Player {
     // 添加歌曲
-    fn add_to_list(&mut self, media: Media, once: bool) -> bool;
+    fn add_to_list(&mut self, media: Media, once: bool) -> Result<(), PlayerError>;
 
-    fn clear_list(&mut self) -> bool;
+    fn clear_list(&mut self) -> Result<(), PlayerError>;
 
     // 播放
-    fn play(&mut self) -> bool;
+    fn play(&mut self) -> Result<(), PlayerError>;
 
     // 下一首
-    fn next(&mut self) -> bool;
+    fn next(&mut self) -> Result<(), PlayerError>;
+
+    // 上一首，播放超过几秒时从头播放
+    fn prev(&mut self) -> Result<(), PlayerError>;
 
     // 停止
-    fn stop(&mut self) -> bool;
+    fn stop(&mut self) -> Result<(), PlayerError>;
 
     // 暂停
-    fn pause(&mut self) -> bool;
+    fn pause(&mut self) -> Result<(), PlayerError>;
 
     // 继续
-    fn resume(&mut self) -> bool;
+    fn resume(&mut self) -> Result<(), PlayerError>;
 
     // 播放进度
     fn get_progress(&self) -> (f32, f32);
@@ -83,315 +224,1442 @@ pub trait Player {
     fn is_paused(&self) -> bool;
 
     // 提供一个接口，用于更新player状态
-    fn tick(&mut self);
+    fn tick(&mut self) -> Result<(), PlayerError>;
 
     // 音量
     fn volume(&self) -> f32;
 
     // 设置音量
-    fn set_volume(&mut self, new_volume: f32) -> bool;
+    fn set_volume(&mut self, new_volume: f32) -> Result<(), PlayerError>;
+
+    // 新歌曲的默认重复次数
+    fn adjust_repetition(&mut self, plus: bool) -> Result<(), PlayerError>;
+
+    // 新歌曲的默认间隔
+    fn adjust_gap(&mut self, inc: f32) -> Result<(), PlayerError>;
+
+    // 跟读模式下间隔与片段长度的比例
+    fn adjust_gap_factor(&mut self, inc: f32) -> Result<(), PlayerError>;
+
+    // 调整列表中某一首的重复次数
+    fn adjust_item_repetition(&mut self, index: usize, plus: bool) -> Result<(), PlayerError>;
+
+    // 调整列表中某一首的间隔
+    fn adjust_item_gap(&mut self, index: usize, inc: f32) -> Result<(), PlayerError>;
+
+    fn adjust_crossfade(&mut self, inc: f32) -> Result<(), PlayerError>;
+
+    // 切换固定间隔/跟读间隔/淡入淡出模式
+    fn switch_transition(&mut self) -> Result<(), PlayerError>;
+
+    // 切换输出设备，None 为系统默认设备
+    fn set_device(&mut self, device: Option<OutputDevice>) -> Result<(), PlayerError>;
+
+    // 切换输出
+    fn set_output(&mut self, output: Box<dyn Output>) -> Result<(), PlayerError>;
+
+    // 跳转到指定位置
+    fn seek_to(&mut self, pos: Duration) -> Result<(), PlayerError>;
+
+    // 前进/后退若干秒
+    fn seek_by(&mut self, secs: i64) -> Result<(), PlayerError>;
+
+    // 设置循环起点
+    fn set_loop_a(&mut self) -> Result<(), PlayerError>;
+
+    // 设置循环终点
+    fn set_loop_b(&mut self) -> Result<(), PlayerError>;
+
+    // 取消循环
+    fn clear_loop(&mut self) -> Result<(), PlayerError>;
+
+    // 播放速度
+    fn speed(&self) -> f32;
+
+    // 设置播放速度（保持音高）
+    fn set_speed(&mut self, speed: f32) -> Result<(), PlayerError>;
+
+    // 切换循环模式：关闭/单曲/列表
+    fn switch_repeat(&mut self) -> Result<(), PlayerError>;
+
+    // 随机播放开关
+    fn toggle_shuffle(&mut self) -> Result<(), PlayerError>;
+
+    // 插入到当前歌曲之后
+    fn insert_next(&mut self, media: Media) -> Result<(), PlayerError>;
+
+    // 按静音切分成句子, 每句一项
+    fn add_segments(&mut self, media: Media) -> Result<(), PlayerError>;
 
-    fn adjust_repetition(&mut self, plus: bool) -> bool;
+    // 把播放列表保存为 M3U8
+    fn save_playlist(&self, path: &str) -> Result<(), PlayerError>;
 
-    fn adjust_gap(&mut self, inc: f32) -> bool;
+    // 跳转到列表中的某一首
+    fn play_at(&mut self, index: usize) -> Result<(), PlayerError>;
+
+    // 从列表中移除
+    fn remove_at(&mut self, index: usize) -> Result<(), PlayerError>;
+
+    // 上移/下移
+    fn move_item(&mut self, index: usize, up: bool) -> Result<(), PlayerError>;
 }
 
 pub struct MusicPlayer {
     // params
     pub current_time: Duration,
     pub total_time: Duration,
+    // wall clock time left at the current speed
+    pub remaining_time: Duration,
     pub play_list: PlayList,
-    stream: OutputStream,
-    stream_handle: OutputStreamHandle,
+    // kept for the whole session, every sink plays on it
+    output: Box<dyn Output>,
+    pub device: Option<OutputDevice>,
     sink: Sink,
+    fade: FadeControl,
+    // the sink fading out during a crossfade
+    outgoing: Option<(Sink, FadeControl)>,
+    position: Position,
+    // passes of the current song left, including this one
+    passes: i32,
+    // play order in shuffle mode, indices into the play list
+    shuffle_order: Vec<usize>,
+    // the current song is in the history already
+    recorded: bool,
+    // loudness gain of the current pass
+    gain: FadeControl,
+    // position and gain of the pass already appended behind the current one
+    queued: Option<(Position, FadeControl, Cancel)>,
+    // station of the current item and the gap before it, appended once it has sound
+    connecting: Option<(Connecting, f32)>,
+    // paused, the sink follows once the fade out is over or by this deadline
+    pausing: Option<Instant>,
+    speed: SpeedControl,
     initialized: bool,
     pub during_gap: bool,
+    // defaults for newly added songs
     pub repetition: i32,
     pub gap: f32,
+    pub crossfade: f32,
+    pub transition: Transition,
+    pub gap_mode: GapMode,
+    pub gap_factor: f32,
+    pub ab_loop: Option<AbLoop>,
+    pub queue_mode: QueueMode,
+    pub history: Vec<HistoryEntry>,
+    pub replay_gain: ReplayGain,
+    loudness: Loudness,
+    pub equalizer: EqControl,
+    pub channels: ChannelControl,
+    pub segmentation: SegmentSettings,
+    segmenter: Segmenter,
+    // files waiting for the segmenter
+    segmenting: Vec<PlayListItem>,
+    prober: Prober,
+    // the thread of the loudness, segment and length caches
+    worker: Worker,
+    // titles seen on each station url
+    stations: HashMap<String, StreamMeta>,
+    // pause once the current item is over, cleared when that happened
+    pub stop_after_current: bool,
+    // volume ramp on pause, resume, stop, skip and quit
+    pub ramp: Duration,
 }
 
 impl Player for MusicPlayer {
-    fn new() -> Self {
-        for dev in cpal::available_hosts() {
-            println!("{:?}", dev);
-        }
-        let (stream, stream_handle) = OutputStream::try_default().unwrap();
-        let sink = Sink::try_new(&stream_handle).unwrap();
-        Self {
-            current_time: Duration::from_secs(0),
-            total_time: Duration::from_secs(0),
-            play_list: PlayList { lists: vec![] },
-            stream,
-            stream_handle,
-            sink,
-            initialized: false,
-            during_gap: false,
-            repetition: 1,
-            gap: 0.0,
+    fn add_to_list(&mut self, media: Media, once: bool) -> Result<(), PlayerError> {
+        match media.src {
+            super::media::Source::Local(path) => self.play_with_file(path, once),
+            super::media::Source::Http(url) => {
+                let item = self.stream_item(url);
+                self.push_item(item, once)
+            }
         }
     }
 
-    fn add_to_list(&mut self, media: Media, once: bool) -> bool {
-        match media.src {
-            super::media::Source::Local(path) => {
-                return self.play_with_file(path, once);
+    fn insert_next(&mut self, media: Media) -> Result<(), PlayerError> {
+        let current = match self.play_list.current {
+            Some(current) => current,
+            None => return self.add_to_list(media, false),
+        };
+        let item = match media.src {
+            super::media::Source::Local(path) => self.new_item(path)?,
+            super::media::Source::Http(url) => self.stream_item(url),
+        };
+        self.insert_item(current + 1, item);
+        self.invalidate_queue();
+        Ok(())
+    }
+
+    fn add_segments(&mut self, media: Media) -> Result<(), PlayerError> {
+        let item = match media.src {
+            super::media::Source::Local(path) => self.new_item(path)?,
+            // a live stream has no end to analyze
+            super::media::Source::Http(url) => {
+                let item = self.stream_item(url);
+                return self.push_item(item, false);
+            }
+        };
+        match self.segmenter.get(item.path.as_str(), &self.segmentation) {
+            Some(segments) => self.push_segments(item, segments),
+            None => {
+                self.segmenter
+                    .request(item.path.as_str(), self.segmentation);
+                self.segmenting.push(item);
+                Ok(())
             }
         }
     }
 
-    fn clear_list(&mut self) -> bool {
-        self.play_list.lists.clear();
-        true
+    fn save_playlist(&self, path: &str) -> Result<(), PlayerError> {
+        let mut entries: Vec<playlist::Entry> = vec![];
+        for item in self.play_list.lists.iter() {
+            let entry = match (&item.segment, &item.meta) {
+                // the sentences of a file go back as the whole file
+                (Some(_), _) => {
+                    if matches!(entries.last(), Some(last) if last.location == item.path) {
+                        continue;
+                    }
+                    let title = Path::new(item.path.as_str())
+                        .file_name()
+                        .map_or(item.name.clone(), |name| name.to_string_lossy().to_string());
+                    playlist::Entry {
+                        location: item.path.clone(),
+                        duration: self.prober.get(item.path.as_str()),
+                        title,
+                    }
+                }
+                (None, Some(meta)) => playlist::Entry {
+                    location: item.path.clone(),
+                    duration: None,
+                    title: meta.station().unwrap_or_else(|| item.name.clone()),
+                },
+                (None, None) => playlist::Entry {
+                    location: item.path.clone(),
+                    duration: item.duration,
+                    title: item.name.clone(),
+                },
+            };
+            entries.push(entry);
+        }
+        playlist::write_m3u8(path, &entries)
+            .map_err(|err| PlayerError::Write(path.to_string(), err.to_string()))
     }
 
-    fn play(&mut self) -> bool {
-        self.sink.play();
-        if let Some(item) = self.play_list.lists.first_mut() {
-            let status = &mut item.status;
-            match status {
-                PlayStatus::Waiting => {
-                    *status = PlayStatus::Playing(Instant::now(), Duration::from_nanos(0));
-                }
-                PlayStatus::Playing(_, _) => {}
-                PlayStatus::Stopped(duration) => {
-                    *status = PlayStatus::Playing(Instant::now(), *duration);
-                }
+    fn play_at(&mut self, index: usize) -> Result<(), PlayerError> {
+        if index >= self.play_list.lists.len() {
+            return Err(PlayerError::NoSuchItem);
+        }
+        self.ab_loop = None;
+        self.move_to(Some(index));
+        if let Err(err) = self.load_head(Duration::from_secs(0), None, 0.0) {
+            return self.skip_broken(err);
+        }
+        self.play()
+    }
+
+    fn remove_at(&mut self, index: usize) -> Result<(), PlayerError> {
+        if index >= self.play_list.lists.len() {
+            return Err(PlayerError::NoSuchItem);
+        }
+        if self.play_list.current == Some(index) {
+            if let Err(err) = self.drop_current() {
+                return self.skip_broken(err);
             }
+            return Ok(());
         }
-        true
+        self.remove_item(index);
+        self.invalidate_queue();
+        Ok(())
     }
 
-    fn next(&mut self) -> bool {
+    fn move_item(&mut self, index: usize, up: bool) -> Result<(), PlayerError> {
         let len = self.play_list.lists.len();
-        
-        if len >= 1 {
-            if self.play_list.lists.first().unwrap().repetition > 1 {
-                let mut top = self.play_list.lists.first_mut().unwrap();
-                top.repetition = top.repetition - 1;
-                top.status = PlayStatus::Playing(Instant::now(), Duration::from_nanos(0));
-                self.during_gap = true;
+        let other = if up {
+            index.checked_sub(1)
+        } else {
+            Some(index + 1).filter(|other| *other < len)
+        };
+        let other = match other {
+            Some(other) if index < len => other,
+            _ => return Err(PlayerError::NoSuchItem),
+        };
+        self.play_list.lists.swap(index, other);
+        let swap = |i: usize| {
+            if i == index {
+                other
+            } else if i == other {
+                index
             } else {
-                self.play_list.lists.remove(0);
-                self.stop();
-            }
-            
-            if !self.play_list.lists.is_empty() {
-                // next song
-                let top_music = self.play_list.lists.first().unwrap();
-                let f = File::open(top_music.path.as_str()).unwrap();
-                let buf_reader = BufReader::new(f);
-                let (stream, stream_handle) = OutputStream::try_default().unwrap();
-                self.stream = stream;
-                self.stream_handle = stream_handle;
-                let volume = self.volume();
-                self.sink = Sink::try_new(&self.stream_handle).unwrap();
-                self.set_volume(volume);
-                self.sink.append(Decoder::new(buf_reader).unwrap());
-                self.during_gap = true;
-                self.play();
+                i
             }
-        } else {
+        };
+        self.play_list.current = self.play_list.current.map(swap);
+        self.shuffle_order = self.shuffle_order.iter().map(|i| swap(*i)).collect();
+        self.invalidate_queue();
+        Ok(())
+    }
+
+    fn clear_list(&mut self) -> Result<(), PlayerError> {
+        self.play_list.lists.clear();
+        self.play_list.current = None;
+        self.shuffle_order.clear();
+        self.ab_loop = None;
+        self.invalidate_queue();
+        Ok(())
+    }
+
+    fn play(&mut self) -> Result<(), PlayerError> {
+        if self.is_paused() {
+            // ramp back up, whether the pause has settled or not
+            self.resume()?;
+        }
+        self.sink.play();
+        if let Some(item) = self.play_list.playing_mut() {
+            item.status = PlayStatus::Playing;
+        }
+        Ok(())
+    }
+
+    fn next(&mut self) -> Result<(), PlayerError> {
+        if self.play_list.current.is_none() {
             // no more sound to play
-            return false;
+            return Err(PlayerError::EmptyQueue);
+        }
+        self.ab_loop = None;
+        let gap = self.gap_after();
+        let next = self.following(true);
+        self.move_to(next);
+        if self.play_list.current.is_some() {
+            // next song
+            if let Err(err) = self.load_head(Duration::from_secs(0), None, gap) {
+                return self.skip_broken(err);
+            }
+            self.play()
+        } else {
+            self.stop()
         }
-        true
     }
 
-    fn stop(&mut self) -> bool {
-        self.sink.stop();
-        true
+    fn prev(&mut self) -> Result<(), PlayerError> {
+        let current = match self.play_list.current {
+            Some(current) => current,
+            None => return Err(PlayerError::EmptyQueue),
+        };
+        let index = if self.position.elapsed() > RESTART_AFTER {
+            current
+        } else {
+            self.preceding().unwrap_or(current)
+        };
+        self.play_at(index)
     }
 
-    fn pause(&mut self) -> bool {
-        self.sink.pause();
-        if let Some(item) = self.play_list.lists.first_mut() {
-            let status = &mut item.status;
-            match status {
-                PlayStatus::Waiting => {}
-                PlayStatus::Playing(instant, duration) => {
-                    *status = PlayStatus::Stopped(duration.add(instant.elapsed()));
-                }
-                PlayStatus::Stopped(_) => {}
+    fn stop(&mut self) -> Result<(), PlayerError> {
+        self.connecting = None;
+        self.pausing = None;
+        if self.sink.empty() || self.sink.is_paused() {
+            // nothing to hear, nothing to ramp
+            self.sink.stop();
+            if let Some((sink, _)) = self.outgoing.take() {
+                sink.stop();
+            }
+            return Ok(());
+        }
+        // fade out on a sink of its own, `tick` drops it once silent
+        let incoming = self.output.new_sink();
+        incoming.set_volume(self.volume());
+        let sink = mem::replace(&mut self.sink, incoming);
+        let fade = mem::replace(&mut self.fade, FadeControl::new(1.0));
+        fade.fade_to(0.0, self.ramp);
+        if let Some((sink, _)) = self.outgoing.replace((sink, fade)) {
+            sink.stop();
+        }
+        Ok(())
+    }
+
+    fn pause(&mut self) -> Result<(), PlayerError> {
+        if !self.is_paused() {
+            // the sink stops pulling samples, so ramp down before `tick` pauses it
+            self.fade.fade_to(0.0, self.ramp);
+            self.pausing = Some(Instant::now() + self.ramp + RAMP_MARGIN);
+        }
+        if let Some(item) = self.play_list.playing_mut() {
+            if item.status == PlayStatus::Playing {
+                item.status = PlayStatus::Stopped;
             }
         }
-        true
+        Ok(())
     }
 
-    fn resume(&mut self) -> bool {
+    fn resume(&mut self) -> Result<(), PlayerError> {
+        self.pausing = None;
+        self.fade.fade_to(1.0, self.ramp);
         self.sink.play();
-        if let Some(item) = self.play_list.lists.first_mut() {
-            let status = &mut item.status;
-            match status {
-                PlayStatus::Waiting => {}
-                PlayStatus::Playing(_, _) => {}
-                PlayStatus::Stopped(duration) => {
-                    *status = PlayStatus::Playing(Instant::now(), *duration);
-                }
+        if let Some((sink, _)) = &self.outgoing {
+            sink.play();
+        }
+        if let Some(item) = self.play_list.playing_mut() {
+            if item.status == PlayStatus::Stopped {
+                item.status = PlayStatus::Playing;
             }
         }
-        return true;
+        Ok(())
     }
 
     fn get_progress(&self) -> (f32, f32) {
-        return (0.0, 0.0);
+        match self.play_list.playing() {
+            Some(item) => (
+                self.position.elapsed().as_secs_f32(),
+                item.duration.unwrap_or_default().as_secs_f32(),
+            ),
+            None => (0.0, 0.0),
+        }
     }
 
     fn is_playing(&self) -> bool {
-        return self.initialized && !self.sink.is_paused() && !self.play_list.lists.is_empty();
+        self.initialized && !self.is_paused() && self.play_list.current.is_some()
     }
 
     fn is_paused(&self) -> bool {
-        return self.initialized && self.sink.is_paused();
+        self.initialized && (self.sink.is_paused() || self.pausing.is_some())
     }
 
-    fn tick(&mut self) {
+    fn tick(&mut self) -> Result<(), PlayerError> {
+        // a measured song's gain is picked up below through `gain_of`
+        while self.loudness.poll().is_some() {}
+        let mut failed = None;
+        while let Some((path, probed)) = self.prober.poll() {
+            match probed {
+                Ok(info) => {
+                    for item in self.play_list.lists.iter_mut() {
+                        if item.path == path && item.segment.is_none() {
+                            item.duration = info.duration;
+                        }
+                    }
+                    self.set_gain_tags(path.as_str(), info.gain_tags);
+                }
+                // those started at once have been reported when they were opened
+                Err(err) if self.drop_unplayable(path.as_str()) => {
+                    failed.get_or_insert(err);
+                }
+                Err(_) => {}
+            }
+        }
+        if let Some(err) = failed {
+            self.invalidate_queue();
+            return Err(err);
+        }
+        while let Some((path, segments)) = self.segmenter.poll() {
+            if let Some(i) = self.segmenting.iter().position(|item| item.path == path) {
+                let item = self.segmenting.remove(i);
+                match segments {
+                    Some(segments) => self.push_segments(item, segments)?,
+                    // can not be analyzed, play it whole
+                    None => self.push_item(item, false)?,
+                }
+            }
+        }
+        if let Some(deadline) = self.pausing {
+            // the ramp down is over, or long enough that it never will be
+            if self.fade.is_settled() || Instant::now() >= deadline {
+                self.pausing = None;
+                self.sink.pause();
+                if let Some((sink, _)) = &self.outgoing {
+                    sink.pause();
+                }
+            }
+        }
+        if let Some((connecting, gap)) = &self.connecting {
+            if let Some(result) = connecting.poll() {
+                let gap = *gap;
+                self.connecting = None;
+                match (result, self.play_list.current) {
+                    (Ok(source), Some(index)) => {
+                        (self.position, self.gain, _) = self.append_decoder(
+                            Box::new(source),
+                            index,
+                            Duration::from_secs(0),
+                            None,
+                            gap,
+                        );
+                    }
+                    (Ok(_), None) => {}
+                    (Err(err), _) => return self.skip_broken(err),
+                }
+            }
+        }
+        if let Some((sink, fade)) = &self.outgoing {
+            if sink.empty() || (fade.is_settled() && fade.gain() <= 0.0) {
+                // crossfade is over
+                sink.stop();
+                self.outgoing = None;
+            }
+        }
         let is_playing = self.is_playing();
-        if let Some(song) = self.play_list.lists.first_mut() {
+        let gain = self.playing_song().map(|song| self.gain_of(song));
+        if let Some(song) = self.play_list.playing_mut() {
             let status = &mut song.status;
             match status {
                 PlayStatus::Waiting => {
                     if is_playing {
-                        *status = PlayStatus::Playing(Instant::now(), Duration::from_nanos(0));
-                        self.during_gap = true;
+                        *status = PlayStatus::Playing;
                     }
                 }
-                PlayStatus::Playing(instant, duration) => {
-                    let now = instant.elapsed().add(duration.clone());
-
-                    if self.during_gap {
-                        if now.ge(&Duration::from_secs_f32(self.gap)){
-                            self.during_gap = false;
-                            *status = PlayStatus::Playing(Instant::now(), Duration::from_nanos(0));
-                            self.play();
-                        } else {
-                            self.sink.pause();
-                        }
+                PlayStatus::Playing | PlayStatus::Stopped => {
+                    if self.position.is_done() {
+                        // every sample of this song (or loop) has been played
+                        return self.finish_current();
                     } else {
-                        if now.ge(&song.duration) {
-                            // next song
-                            self.next();
-                        } else {
-                            // update status
-                            self.current_time = now;
-                            self.total_time = song.duration.clone();
+                        // update status, position is in track time whatever the speed
+                        self.during_gap = !self.position.started();
+                        if !self.recorded && self.position.started() {
+                            self.recorded = true;
+                            self.history.push(HistoryEntry {
+                                name: song.name.clone(),
+                                played_at: Local::now(),
+                            });
                         }
+                        if let Some(gain) = gain {
+                            if (gain - self.gain.target()).abs() > GAIN_EPSILON {
+                                // a measurement came in or the settings changed
+                                self.gain.fade_to(gain, GAIN_GLIDE);
+                            }
+                        }
+                        self.current_time = self.position.elapsed();
+                        self.total_time = song.duration.unwrap_or_default();
+                        self.remaining_time = self
+                            .total_time
+                            .saturating_sub(self.current_time)
+                            .div_f32(self.speed.get());
+                        self.preload()?;
                     }
                 }
-                PlayStatus::Stopped(dur) => {
-                    self.current_time = dur.clone();
-                    self.total_time = song.duration.clone();
-                }
             }
         } else {
             // stop player when no sounds
-            if self.play_list.lists.is_empty() {
-                self.stop();
+            if self.play_list.current.is_none() {
+                self.stop()?;
             }
         }
+        Ok(())
     }
 
     fn volume(&self) -> f32 {
-        return self.sink.volume();
+        self.sink.volume()
     }
 
-    fn set_volume(&mut self, new_volume: f32) -> bool {
+    fn set_volume(&mut self, new_volume: f32) -> Result<(), PlayerError> {
         self.sink.set_volume(new_volume);
-        true
+        if let Some((sink, _)) = &self.outgoing {
+            sink.set_volume(new_volume);
+        }
+        Ok(())
     }
 
-    fn adjust_repetition(&mut self, plus: bool) -> bool {
+    fn adjust_repetition(&mut self, plus: bool) -> Result<(), PlayerError> {
         if plus {
-            self.repetition = self.repetition + 1;
+            self.repetition += 1;
         } else if self.repetition > 1 {
-            self.repetition = self.repetition - 1
+            self.repetition -= 1
         }
-        for item in &mut self.play_list.lists {
-            item.repetition = self.repetition;
-        }
-        true
+        Ok(())
     }
 
-    fn adjust_gap(&mut self, inc: f32) -> bool {
+    fn adjust_gap(&mut self, inc: f32) -> Result<(), PlayerError> {
         if self.gap + inc >= 0.0 {
-            self.gap = self.gap + inc;
+            self.gap += inc;
+        }
+        Ok(())
+    }
+
+    fn adjust_gap_factor(&mut self, inc: f32) -> Result<(), PlayerError> {
+        if self.gap_factor + inc >= 0.0 {
+            self.gap_factor += inc;
+        }
+        self.invalidate_queue();
+        Ok(())
+    }
+
+    fn adjust_item_repetition(&mut self, index: usize, plus: bool) -> Result<(), PlayerError> {
+        let item = match self.play_list.lists.get_mut(index) {
+            Some(item) => item,
+            None => return Err(PlayerError::NoSuchItem),
+        };
+        let delta = if plus {
+            1
+        } else if item.repetition > 1 {
+            -1
+        } else {
+            0
+        };
+        item.repetition += delta;
+        if self.play_list.current == Some(index) {
+            // passes already played still count
+            self.passes = (self.passes + delta).max(1);
+        }
+        self.invalidate_queue();
+        Ok(())
+    }
+
+    fn adjust_item_gap(&mut self, index: usize, inc: f32) -> Result<(), PlayerError> {
+        let item = match self.play_list.lists.get_mut(index) {
+            Some(item) => item,
+            None => return Err(PlayerError::NoSuchItem),
+        };
+        if item.gap + inc >= 0.0 {
+            item.gap += inc;
+        }
+        self.invalidate_queue();
+        Ok(())
+    }
+
+    fn adjust_crossfade(&mut self, inc: f32) -> Result<(), PlayerError> {
+        self.crossfade = (self.crossfade + inc).clamp(MIN_CROSSFADE, MAX_CROSSFADE);
+        self.invalidate_queue();
+        Ok(())
+    }
+
+    fn switch_transition(&mut self) -> Result<(), PlayerError> {
+        match (self.transition, self.gap_mode) {
+            (Transition::Gap, GapMode::Fixed) => self.gap_mode = GapMode::Shadowing,
+            (Transition::Gap, GapMode::Shadowing) => self.transition = Transition::Crossfade,
+            (Transition::Crossfade, _) => {
+                self.transition = Transition::Gap;
+                self.gap_mode = GapMode::Fixed;
+            }
+        }
+        self.invalidate_queue();
+        Ok(())
+    }
+
+    fn set_device(&mut self, device: Option<OutputDevice>) -> Result<(), PlayerError> {
+        let output = DeviceOutput::open(device.as_ref()).map_err(PlayerError::Device)?;
+        self.device = device;
+        self.set_output(Box::new(output))
+    }
+
+    fn set_output(&mut self, output: Box<dyn Output>) -> Result<(), PlayerError> {
+        let volume = self.volume();
+        self.stop()?;
+        // the old output goes away, nothing would drain a fading sink
+        if let Some((sink, _)) = self.outgoing.take() {
+            sink.stop();
+        }
+        self.output = output;
+        self.sink = self.output.new_sink();
+        self.set_volume(volume)?;
+        if let Some(item) = self.play_list.playing() {
+            // carry on from the same position on the new device
+            let paused = item.status == PlayStatus::Stopped;
+            let pos = match item.is_stream() {
+                // reconnect
+                true => Duration::from_secs(0),
+                false => self.position.elapsed(),
+            };
+            self.seek_to(pos)?;
+            if paused {
+                self.sink.pause();
+            }
+        }
+        Ok(())
+    }
+
+    fn seek_to(&mut self, pos: Duration) -> Result<(), PlayerError> {
+        let paused = self.is_paused();
+        let duration = match self.play_list.playing() {
+            Some(item) if item.is_stream() && !pos.is_zero() => {
+                return Err(PlayerError::NotSeekable)
+            }
+            Some(item) => item.duration,
+            None => return Err(PlayerError::EmptyQueue),
+        };
+        let pos = duration.map_or(pos, |duration| pos.min(duration));
+        // leaving the loop region cancels it
+        let end = match &self.ab_loop {
+            Some(AbLoop { b: Some(b), .. }) if pos < *b => Some(*b),
+            Some(AbLoop { b: Some(_), .. }) => {
+                self.ab_loop = None;
+                None
+            }
+            _ => None,
+        };
+        self.load_head(pos, end, 0.0)?;
+        if paused {
+            self.sink.pause();
+        }
+        self.current_time = pos;
+        self.total_time = duration.unwrap_or_default();
+        Ok(())
+    }
+
+    fn seek_by(&mut self, secs: i64) -> Result<(), PlayerError> {
+        if self.play_list.current.is_none() {
+            return Err(PlayerError::EmptyQueue);
+        }
+        let current = self.position.elapsed();
+        let offset = Duration::from_secs(secs.unsigned_abs());
+        let target = if secs >= 0 {
+            current.add(offset)
+        } else {
+            current.saturating_sub(offset)
+        };
+        self.seek_to(target)
+    }
+
+    fn set_loop_a(&mut self) -> Result<(), PlayerError> {
+        match self.play_list.playing() {
+            Some(item) if item.is_stream() => return Err(PlayerError::NotSeekable),
+            Some(_) => {}
+            None => return Err(PlayerError::EmptyQueue),
+        }
+        let a = self.position.elapsed();
+        if self.ab_loop.as_ref().and_then(|ab| ab.b).is_some() {
+            // restart the region at the new a
+            self.ab_loop = None;
+            self.seek_to(a)?;
+        }
+        self.ab_loop = Some(AbLoop {
+            a,
+            b: None,
+            remaining: self.playing_song().map_or(1, |item| item.repetition),
+        });
+        Ok(())
+    }
+
+    fn set_loop_b(&mut self) -> Result<(), PlayerError> {
+        if self.play_list.current.is_none() {
+            return Err(PlayerError::EmptyQueue);
+        }
+        let b = self.position.elapsed();
+        let a = match &self.ab_loop {
+            Some(ab) if b > ab.a => ab.a,
+            _ => return Err(PlayerError::NoLoopStart),
+        };
+        self.ab_loop = Some(AbLoop {
+            a,
+            b: Some(b),
+            remaining: self.playing_song().map_or(1, |item| item.repetition),
+        });
+        let paused = self.is_paused();
+        if let Err(err) = self.load_head(a, Some(b), 0.0) {
+            self.ab_loop = None;
+            return Err(err);
+        }
+        if paused {
+            self.sink.pause();
+        }
+        Ok(())
+    }
+
+    fn clear_loop(&mut self) -> Result<(), PlayerError> {
+        if let Some(AbLoop { b: Some(_), .. }) = self.ab_loop {
+            // keep playing from where we are without the end mark
+            self.ab_loop = None;
+            return self.seek_to(self.position.elapsed());
+        }
+        self.ab_loop = None;
+        Ok(())
+    }
+
+    fn speed(&self) -> f32 {
+        self.speed.get()
+    }
+
+    fn set_speed(&mut self, speed: f32) -> Result<(), PlayerError> {
+        self.speed.set(speed);
+        Ok(())
+    }
+
+    fn switch_repeat(&mut self) -> Result<(), PlayerError> {
+        self.queue_mode = match self.queue_mode {
+            QueueMode::Off => QueueMode::RepeatOne,
+            QueueMode::RepeatOne => QueueMode::RepeatAll,
+            QueueMode::RepeatAll | QueueMode::Shuffle => QueueMode::Off,
+        };
+        self.invalidate_queue();
+        Ok(())
+    }
+
+    fn toggle_shuffle(&mut self) -> Result<(), PlayerError> {
+        if self.queue_mode == QueueMode::Shuffle {
+            self.queue_mode = QueueMode::Off;
+        } else {
+            self.queue_mode = QueueMode::Shuffle;
+            self.reshuffle();
         }
-        true
+        self.invalidate_queue();
+        Ok(())
     }
 }
 
 impl MusicPlayer {
+    // 初始化
+    pub fn with_output(output: Box<dyn Output>) -> Self {
+        let sink = output.new_sink();
+        let worker = Worker::new();
+        Self {
+            current_time: Duration::from_secs(0),
+            total_time: Duration::from_secs(0),
+            remaining_time: Duration::from_secs(0),
+            play_list: PlayList {
+                lists: vec![],
+                current: None,
+            },
+            output,
+            device: None,
+            sink,
+            fade: FadeControl::new(1.0),
+            outgoing: None,
+            position: Position::default(),
+            passes: 0,
+            shuffle_order: vec![],
+            recorded: false,
+            gain: FadeControl::new(1.0),
+            queued: None,
+            connecting: None,
+            pausing: None,
+            speed: SpeedControl::new(1.0),
+            initialized: false,
+            during_gap: false,
+            repetition: 1,
+            gap: 0.0,
+            crossfade: 3.0,
+            transition: Transition::Gap,
+            gap_mode: GapMode::Fixed,
+            gap_factor: 1.0,
+            ab_loop: None,
+            queue_mode: QueueMode::Off,
+            history: vec![],
+            replay_gain: ReplayGain::default(),
+            loudness: Loudness::new(&worker),
+            equalizer: EqControl::new([0.0; BANDS], true),
+            channels: ChannelControl::new(),
+            segmentation: SegmentSettings::default(),
+            segmenter: Segmenter::new(&worker),
+            segmenting: vec![],
+            prober: Prober::new(&worker),
+            worker,
+            stations: HashMap::new(),
+            stop_after_current: false,
+            ramp: Duration::from_millis(40),
+        }
+    }
+
+    // ramp everything down and wait for it, before the app exits
+    pub fn shutdown(&mut self) {
+        self.worker.flush();
+        self.fade.fade_to(0.0, self.ramp);
+        if let Some((_, fade)) = &self.outgoing {
+            fade.fade_to(0.0, self.ramp);
+        }
+        if !self.sink.is_paused() {
+            wait_for(&self.fade, self.ramp);
+        }
+    }
+
+    // take the current pass down to silence, for the sleep timer
+    pub fn fade_out(&mut self, over: Duration) {
+        self.fade.fade_to(0.0, over);
+    }
+
+    pub fn is_fading_out(&self) -> bool {
+        self.fade.target() <= 0.0
+    }
+
+    // pause once the current item is over, or no longer
+    pub fn set_stop_after_current(&mut self, stop: bool) -> Result<(), PlayerError> {
+        if self.stop_after_current == stop {
+            return Ok(());
+        }
+        self.stop_after_current = stop;
+        // whatever was queued behind the current item no longer follows it, or does again
+        self.invalidate_queue();
+        Ok(())
+    }
+
+    // undo `fade_out` at once, a pause keeps its own ramp
+    pub fn restore_fade(&mut self) {
+        if self.is_fading_out() && !self.is_paused() {
+            self.fade.fade_to(1.0, Duration::ZERO);
+        }
+    }
+
+    // the current item ends with this pass
+    fn is_last_pass(&self) -> bool {
+        let looping = matches!(self.ab_loop, Some(AbLoop { b: Some(_), .. }));
+        !looping && self.passes <= 1
+    }
+
     pub fn playing_song(&self) -> Option<&PlayListItem> {
-        return self.play_list.lists.first();
+        self.play_list.playing()
     }
 
-    fn play_with_file(&mut self, path: String, once: bool) -> bool {
-        let duration: Duration;
-        if path.ends_with(".mp3") {
-            let dur = mp3_duration::from_path(path.clone());
-            match dur {
-                Ok(dur) => {
-                    duration = dur;
-                }
-                Err(err) => {
-                    // EOF catch
-                    duration = err.at_duration;
-                    if duration.is_zero() {
-                        return false;
-                    }
+    // the song after the current one, `skip` leaves a repeated song
+    fn following(&self, skip: bool) -> Option<usize> {
+        let current = self.play_list.current?;
+        let len = self.play_list.lists.len();
+        match self.queue_mode {
+            QueueMode::RepeatOne if !skip => Some(current),
+            QueueMode::Off => Some(current + 1).filter(|next| *next < len),
+            QueueMode::RepeatOne | QueueMode::RepeatAll => Some((current + 1) % len),
+            QueueMode::Shuffle => {
+                let pos = self.shuffle_order.iter().position(|i| *i == current)?;
+                self.shuffle_order.get(pos + 1).copied()
+            }
+        }
+    }
+
+    // the song before the current one in play order
+    fn preceding(&self) -> Option<usize> {
+        let current = self.play_list.current?;
+        let len = self.play_list.lists.len();
+        match self.queue_mode {
+            QueueMode::Off => current.checked_sub(1),
+            QueueMode::RepeatOne | QueueMode::RepeatAll => Some((current + len - 1) % len),
+            QueueMode::Shuffle => {
+                let pos = self.shuffle_order.iter().position(|i| *i == current)?;
+                pos.checked_sub(1).map(|pos| self.shuffle_order[pos])
+            }
+        }
+    }
+
+    // make `index` the current song, None once the queue is over
+    fn move_to(&mut self, index: Option<usize>) {
+        if let Some(item) = self.play_list.playing_mut() {
+            item.status = PlayStatus::Waiting;
+        }
+        self.play_list.current = index;
+        self.recorded = false;
+        self.passes = self.play_list.playing().map_or(0, |item| item.repetition);
+    }
+
+    // new shuffle order starting from the current song
+    fn reshuffle(&mut self) {
+        let current = self.play_list.current;
+        let mut rest: Vec<usize> = (0..self.play_list.lists.len())
+            .filter(|i| Some(*i) != current)
+            .collect();
+        rest.shuffle(&mut thread_rng());
+        self.shuffle_order = current.into_iter().chain(rest).collect();
+    }
+
+    // put a song at `at`, it plays right after the current one in shuffle mode too
+    fn insert_item(&mut self, at: usize, item: PlayListItem) {
+        self.play_list.lists.insert(at, item);
+        let shift = |i: usize| if i >= at { i + 1 } else { i };
+        self.play_list.current = self.play_list.current.map(shift);
+        self.shuffle_order = self.shuffle_order.iter().map(|i| shift(*i)).collect();
+        let pos = self
+            .shuffle_order
+            .iter()
+            .position(|i| Some(*i) == self.play_list.current)
+            .map_or(0, |pos| pos + 1);
+        self.shuffle_order.insert(pos, at);
+    }
+
+    // drop a song from the list, keeping the indices pointing at the same songs
+    fn remove_item(&mut self, index: usize) {
+        self.play_list.lists.remove(index);
+        let shift = |i: usize| if i > index { i - 1 } else { i };
+        self.shuffle_order.retain(|i| *i != index);
+        self.shuffle_order = self.shuffle_order.iter().map(|i| shift(*i)).collect();
+        self.play_list.current = match self.play_list.current {
+            Some(current) if current == index => None,
+            current => current.map(shift),
+        };
+    }
+
+    // silence after a pass of `pass` length of `item`
+    pub fn gap_for(&self, item: &PlayListItem, pass: Duration) -> f32 {
+        match (self.transition, self.gap_mode) {
+            (Transition::Crossfade, _) => 0.0,
+            (Transition::Gap, GapMode::Fixed) => item.gap,
+            (Transition::Gap, GapMode::Shadowing) => {
+                self.gap_factor * pass.as_secs_f32() + item.gap
+            }
+        }
+    }
+
+    // silence after a pass of the current song
+    pub fn gap_after(&self) -> f32 {
+        let item = match self.play_list.playing() {
+            Some(item) => item,
+            None => return 0.0,
+        };
+        let pass = match &self.ab_loop {
+            Some(AbLoop { a, b: Some(b), .. }) => b.saturating_sub(*a),
+            _ => item.duration.unwrap_or_default(),
+        };
+        self.gap_for(item, pass)
+    }
+
+    // what plays once the current pass is over: (index, start, end, gap)
+    fn upcoming(&self) -> Option<(usize, Duration, Option<Duration>, f32)> {
+        let current = self.play_list.current?;
+        let gap = self.gap_after();
+        if let Some(AbLoop {
+            a,
+            b: Some(b),
+            remaining,
+        }) = self.ab_loop
+        {
+            if remaining > 1 {
+                return Some((current, a, Some(b), gap));
+            }
+            // loop done, play on past b
+            return Some((current, b, None, 0.0));
+        }
+        if self.passes > 1 {
+            return Some((current, Duration::from_secs(0), None, gap));
+        }
+        let next = self.following(false)?;
+        Some((next, Duration::from_secs(0), None, gap))
+    }
+
+    // move the list on to what `upcoming` returned
+    fn advance(&mut self) {
+        if let Some(ab) = &mut self.ab_loop {
+            if ab.b.is_some() {
+                if ab.remaining > 1 {
+                    ab.remaining -= 1;
+                } else {
+                    self.ab_loop = None;
                 }
+                return;
             }
+        }
+        if self.play_list.current.is_none() {
+            return;
+        }
+        if self.passes > 1 {
+            self.passes -= 1;
         } else {
-            if let Ok(f) = File::open(path.as_str()) {
-                let dec = Decoder::new(f);
-                if let Ok(dec) = dec {
-                    if let Some(dur) = dec.total_duration() {
-                        duration = dur;
-                    } else {
-                        return false;
-                    }
-                } else {
-                    return false;
+            let next = self.following(false);
+            self.move_to(next);
+        }
+    }
+
+    // append the next pass behind the current one so there is no gap between them
+    fn preload(&mut self) -> Result<(), PlayerError> {
+        if self.queued.is_some() {
+            return Ok(());
+        }
+        if self.stop_after_current && self.is_last_pass() {
+            // nothing follows, `finish_current` pauses instead
+            return Ok(());
+        }
+        let end = match &self.ab_loop {
+            Some(AbLoop { b: Some(b), .. }) => *b,
+            // of unknown length, the next one is loaded once it has ended
+            _ => match self.play_list.playing().and_then(|item| item.duration) {
+                Some(duration) => duration,
+                None => return Ok(()),
+            },
+        };
+        let left = end.saturating_sub(self.position.elapsed());
+        let looping = matches!(self.ab_loop, Some(AbLoop { b: Some(_), .. }));
+        if self.transition == Transition::Crossfade && !looping {
+            if self.is_paused() {
+                // the incoming sink would start playing
+                return Ok(());
+            }
+            // never overlap more than a third of the song
+            let duration = match self.play_list.playing().and_then(|item| item.duration) {
+                Some(duration) => duration,
+                None => return Ok(()),
+            };
+            let length = Duration::from_secs_f32(self.crossfade).min(duration / 3);
+            if left.div_f32(self.speed.get()) <= length {
+                return self.start_crossfade(length);
+            }
+            return Ok(());
+        }
+        if left > PRELOAD_AHEAD {
+            return Ok(());
+        }
+        if let Some((index, start, end, gap)) = self.upcoming() {
+            if self.play_list.lists[index].is_stream() {
+                // a station connects once the current pass is over
+                return Ok(());
+            }
+            if let Ok(decoder) = self.open_item(index, start) {
+                self.queued = Some(self.append_decoder(decoder, index, start, end, gap));
+            }
+        }
+        Ok(())
+    }
+
+    // what was queued no longer matches `upcoming`, drop it for `preload` to queue again
+    fn invalidate_queue(&mut self) {
+        if self.position.is_done() {
+            // the queued pass is playing already, `finish_current` takes it on
+            return;
+        }
+        if let Some((_, _, cancel)) = self.queued.take() {
+            cancel.cancel();
+        }
+    }
+
+    // start the next pass on a new sink while the current one fades out
+    fn start_crossfade(&mut self, length: Duration) -> Result<(), PlayerError> {
+        let (index, start, end) = match self.upcoming() {
+            Some((index, start, end, _)) => (index, start, end),
+            None => return Ok(()),
+        };
+        if self.play_list.lists[index].is_stream() {
+            // stations are not opened ahead, it follows once this pass is over
+            return Ok(());
+        }
+        let decoder = match self.open_item(index, start) {
+            Ok(decoder) => decoder,
+            Err(err) => {
+                if self.play_list.current != Some(index) {
+                    // crossfade into the one after it instead
+                    self.remove_item(index);
                 }
-            } else {
-                return false;
-            }
-        }
-
-        // open
-        match File::open(path.as_str()) {
-            Ok(f) => {
-                let path = Path::new(path.as_str());
-                let file_name = path.file_name().unwrap().to_string_lossy().to_string();
-                // Result<(stream,streamHanlde),std::error:Error>
-                if once || self.play_list.lists.is_empty() {
-                    // rebuild
-                    self.stop();
-                    let buf_reader = BufReader::new(f);
-                    let sink = self.stream_handle.play_once(buf_reader).unwrap();
-                    self.sink = sink;
-                    self.clear_list();
+                return Err(err);
+            }
+        };
+        self.fade.fade_to(0.0, length);
+        let incoming = self.output.new_sink();
+        incoming.set_volume(self.volume());
+        let sink = mem::replace(&mut self.sink, incoming);
+        let fade = mem::replace(&mut self.fade, FadeControl::new(0.0));
+        if let Some((sink, _)) = self.outgoing.replace((sink, fade)) {
+            sink.stop();
+        }
+        self.fade.fade_to(1.0, length);
+        (self.position, self.gain, _) = self.append_decoder(decoder, index, start, end, 0.0);
+        // the incoming pass is the current one from now on
+        self.advance();
+        self.play()
+    }
+
+    // song or loop pass finished, decide what to play next
+    fn finish_current(&mut self) -> Result<(), PlayerError> {
+        if let Some((position, gain, _)) = self.queued.take() {
+            // the sink has already moved on to it
+            let paused = self.is_paused();
+            self.advance();
+            self.position = position;
+            self.gain = gain;
+            if paused {
+                // over while ramping down, the pause carries on with the next pass
+                if let Some(item) = self.play_list.playing_mut() {
+                    item.status = PlayStatus::Stopped;
                 }
-                let mut state = ListState::default();
-                state.select(Some(0));
-                self.play_list.lists.push(PlayListItem {
-                    name: file_name,
-                    duration,
-                    current_pos: Duration::from_secs(0),
-                    status: PlayStatus::Waiting,
-                    path: path.to_string_lossy().to_string(),
-                    repetition: self.repetition,
-                });
-                if !self.initialized {
-                    self.initialized = true;
+                return Ok(());
+            }
+            return self.play();
+        }
+        if self.stop_after_current && self.is_last_pass() {
+            // sleep: cue up what comes next but leave it paused
+            self.stop_after_current = false;
+            self.advance();
+            if self.play_list.current.is_none() {
+                return self.stop();
+            }
+            self.load_head(Duration::from_secs(0), None, 0.0)?;
+            return self.pause();
+        }
+        match self.upcoming() {
+            Some((_, start, end, gap)) => {
+                self.advance();
+                if let Err(err) = self.load_head(start, end, gap) {
+                    return self.skip_broken(err);
                 }
-                self.during_gap = false;
-                self.play();
-                self.tick();
-                return true;
+                self.play()
+            }
+            None => {
+                self.advance();
+                self.stop()
             }
-            Err(_) => false,
         }
     }
+
+    // remove the current song and start the one after it
+    fn drop_current(&mut self) -> Result<(), PlayerError> {
+        let current = match self.play_list.current {
+            Some(current) => current,
+            None => return Ok(()),
+        };
+        self.ab_loop = None;
+        let next = self.following(true).filter(|next| *next != current);
+        self.remove_item(current);
+        self.move_to(next.map(|next| if next > current { next - 1 } else { next }));
+        if self.play_list.current.is_none() {
+            return self.stop();
+        }
+        self.load_head(Duration::from_secs(0), None, 0.0)?;
+        self.play()
+    }
+
+    // the head can not be loaded, drop it and play the first song after it that can
+    fn skip_broken(&mut self, err: PlayerError) -> Result<(), PlayerError> {
+        while self.drop_current().is_err() {}
+        Err(err)
+    }
+
+    // rebuild the sink with the first song of the list, playing from `start` to `end`
+    fn load_head(
+        &mut self,
+        start: Duration,
+        end: Option<Duration>,
+        gap: f32,
+    ) -> Result<(), PlayerError> {
+        let index = match self.play_list.current {
+            Some(index) => index,
+            None => return Err(PlayerError::EmptyQueue),
+        };
+        // connecting to a station may take a while, `tick` appends it once it has sound
+        let decoder = match self.play_list.lists[index].is_stream() {
+            true => None,
+            false => Some(self.open_item(index, start)?),
+        };
+        // the old sink can not be reused after stop
+        let volume = self.volume();
+        self.stop()?;
+        self.sink = self.output.new_sink();
+        // starting mid-song would click, so ramp up
+        self.fade = FadeControl::new(0.0);
+        self.fade.fade_to(1.0, self.ramp);
+        self.set_volume(volume)?;
+        self.queued = None;
+        match decoder {
+            Some(decoder) => {
+                (self.position, self.gain, _) =
+                    self.append_decoder(decoder, index, start, end, gap);
+            }
+            None => {
+                let item = &self.play_list.lists[index];
+                let meta = item.meta.clone().unwrap_or_default();
+                self.position = Position::new(Duration::from_secs(0));
+                self.connecting = Some((connect(item.path.as_str(), meta), gap));
+            }
+        }
+        self.during_gap = gap > 0.0;
+        Ok(())
+    }
+
+    // queue a decoder of song `index` on the sink, preceded by `gap` seconds of silence
+    fn append_decoder(
+        &mut self,
+        decoder: Track,
+        index: usize,
+        start: Duration,
+        end: Option<Duration>,
+        gap: f32,
+    ) -> (Position, FadeControl, Cancel) {
+        if self.sink.empty() {
+            // let the frame started on the placeholder run into silence, not the song
+            let channels = decoder.channels().max(1);
+            let len = LEAD_IN.div_ceil(channels as usize) * channels as usize;
+            let silence = Zero::<i16>::new_samples(channels, decoder.sample_rate(), len);
+            self.sink.append(silence);
+        }
+        // the gap goes along with the pass when it is dropped from the queue
+        let cancel = Cancel::default();
+        if gap > 0.0 {
+            // silence between repetitions
+            let silence = Zero::<i16>::new(decoder.channels(), decoder.sample_rate())
+                .take_duration(Duration::from_secs_f32(gap));
+            self.sink.append(Cancellable::new(silence, cancel.clone()));
+        }
+        // times of a segment are relative to where it starts in the file,
+        // the decoder is at `start` already
+        let position = Position::new(start);
+        let end = match self.play_list.lists[index].segment {
+            Some((from, to)) => Some(end.map_or(to - from, |end| end.min(to - from))),
+            None => end,
+        };
+        let decoder: Track = match end {
+            Some(end) => Box::new(decoder.take_duration(end.saturating_sub(start))),
+            None => decoder,
+        };
+        let stretched = TimeStretch::new(
+            Tracked::new(decoder, position.clone()).convert_samples(),
+            self.speed.clone(),
+        );
+        let equalized = Equalizer::new(
+            ChannelMixer::new(stretched, self.channels.clone()),
+            self.equalizer.clone(),
+        );
+        // loudness gain in front of the transport fades
+        let gain = FadeControl::new(self.gain_of(&self.play_list.lists[index]));
+        let faded = Fade::new(Fade::new(equalized, gain.clone()), self.fade.clone());
+        // done once the stretcher has let go of its last samples too
+        let done = Done::new(faded, position.clone());
+        self.sink.append(Cancellable::new(done, cancel.clone()));
+        (position, gain, cancel)
+    }
+
+    // linear ReplayGain of a song, unity until an untagged song has been measured
+    pub fn gain_of(&self, item: &PlayListItem) -> f32 {
+        let tags = match &item.gain_tags {
+            Some(tags) => tags,
+            None => return 1.0,
+        };
+        let measured = match tags.is_complete() {
+            true => None,
+            false => self.loudness.get(item.path.as_str()),
+        };
+        self.replay_gain.linear(tags, measured)
+    }
+
+    // the tags of every item of a file, an untagged one is measured in the background
+    fn set_gain_tags(&mut self, path: &str, tags: GainTags) {
+        let items = self
+            .play_list
+            .lists
+            .iter_mut()
+            .chain(self.segmenting.iter_mut());
+        for item in items.filter(|item| item.path == path) {
+            item.gain_tags = Some(tags);
+        }
+        if !tags.is_complete() {
+            self.loudness.request(path);
+        }
+    }
+
+    fn play_with_file(&mut self, path: String, once: bool) -> Result<(), PlayerError> {
+        let item = self.new_item(path)?;
+        self.push_item(item, once)
+    }
+
+    // one item per segment, named after the file
+    fn push_segments(&mut self, item: PlayListItem, segments: Segments) -> Result<(), PlayerError> {
+        let count = segments.len();
+        for (i, (from, to)) in segments.into_iter().enumerate() {
+            self.push_item(
+                PlayListItem {
+                    name: format!("{} [{}/{}]", item.name, i + 1, count),
+                    duration: Some(to.saturating_sub(from)),
+                    status: PlayStatus::Waiting,
+                    path: item.path.clone(),
+                    repetition: item.repetition,
+                    gap: item.gap,
+                    gain_tags: item.gain_tags,
+                    segment: Some((from, to)),
+                    meta: None,
+                },
+                false,
+            )?;
+        }
+        Ok(())
+    }
+
+    // add to the end of the list, starting it when nothing is playing,
+    // a paused list stays paused
+    fn push_item(&mut self, item: PlayListItem, once: bool) -> Result<(), PlayerError> {
+        let rebuild = once || self.play_list.current.is_none();
+        if once {
+            self.clear_list()?;
+        }
+        self.play_list.lists.push(item);
+        let index = self.play_list.lists.len() - 1;
+        if rebuild {
+            self.move_to(Some(index));
+            self.reshuffle();
+            if let Err(err) = self.load_head(Duration::from_secs(0), None, 0.0) {
+                self.remove_item(index);
+                return Err(err);
+            }
+            self.initialized = true;
+            self.play()?;
+        } else {
+            // somewhere among the songs not played yet
+            let pos = self
+                .shuffle_order
+                .iter()
+                .position(|i| Some(*i) == self.play_list.current)
+                .map_or(0, |pos| pos + 1);
+            let at = thread_rng().gen_range(pos..=self.shuffle_order.len());
+            self.shuffle_order.insert(at, index);
+        }
+        self.tick()
+    }
+
+    // take every waiting item of a file out of the list, false when there was none
+    fn drop_unplayable(&mut self, path: &str) -> bool {
+        let count = self.play_list.lists.len() + self.segmenting.len();
+        for index in (0..self.play_list.lists.len()).rev() {
+            let item = &self.play_list.lists[index];
+            // the current one has been opened already
+            if item.path == path && self.play_list.current != Some(index) {
+                self.remove_item(index);
+            }
+        }
+        self.segmenting.retain(|item| item.path != path);
+        self.play_list.lists.len() + self.segmenting.len() < count
+    }
+
+    // file `index` positioned at `start`, relative to the segment if it is one
+    fn open_item(&mut self, index: usize, start: Duration) -> Result<Track, PlayerError> {
+        if self.play_list.lists[index].gain_tags.is_none() {
+            // opened before the prober got to it, the gain is needed right away
+            let path = self.play_list.lists[index].path.clone();
+            self.set_gain_tags(path.as_str(), GainTags::read(path.as_str()));
+        }
+        let item = &self.play_list.lists[index];
+        let from = item
+            .segment
+            .map_or(Duration::from_secs(0), |(from, _)| from);
+        decoder::open_at(item.path.as_str(), from + start)
+    }
+
+    // a radio station for the play list, its length is unknown
+    fn stream_item(&mut self, url: String) -> PlayListItem {
+        let meta = self.stations.entry(url.clone()).or_default().clone();
+        PlayListItem {
+            name: url.clone(),
+            duration: None,
+            status: PlayStatus::Waiting,
+            path: url,
+            repetition: self.repetition,
+            gap: self.gap,
+            gain_tags: None,
+            segment: None,
+            meta: Some(meta),
+        }
+    }
+
+    // a file for the play list, checked and measured in the background
+    fn new_item(&self, path: String) -> Result<PlayListItem, PlayerError> {
+        if !Path::new(path.as_str()).is_file() {
+            return Err(PlayerError::FileMissing(path));
+        }
+        // absolute, so the song still plays and is cached under one name after a cd
+        let path = match fs::canonicalize(path.as_str()) {
+            Ok(path) => path.to_string_lossy().to_string(),
+            Err(_) => return Err(PlayerError::FileMissing(path)),
+        };
+        // shown until the prober is done, what can not be played is dropped then
+        let duration = self.prober.get(path.as_str());
+        self.prober.request(path.as_str());
+
+        let file_name = match Path::new(path.as_str()).file_name() {
+            Some(name) => name.to_string_lossy().to_string(),
+            None => return Err(PlayerError::FileMissing(path)),
+        };
+        Ok(PlayListItem {
+            name: file_name,
+            duration,
+            status: PlayStatus::Waiting,
+            path,
+            repetition: self.repetition,
+            gap: self.gap,
+            gain_tags: None,
+            segment: None,
+            meta: None,
+        })
+    }
+}
+
+// block until `fade` has reached its target, or long enough that it never will
+fn wait_for(fade: &FadeControl, ramp: Duration) {
+    let deadline = Instant::now() + ramp + RAMP_MARGIN;
+    while !fade.is_settled() && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(2));
+    }
 }
 
 impl Drop for MusicPlayer {
@@ -399,3 +1667,560 @@ impl Drop for MusicPlayer {
         // println!()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        path::{Path, PathBuf},
+        process, thread,
+        time::{Duration, Instant},
+    };
+
+    use hound::{SampleFormat, WavReader, WavSpec, WavWriter};
+
+    use super::{
+        AbLoop, GapMode, MusicPlayer, PlayStatus, Player, PlayerError, QueueMode, Transition,
+        MAX_CROSSFADE, MIN_CROSSFADE,
+    };
+    use crate::media::{
+        loudness::GainMode,
+        media::{Media, Source},
+        output::{self, OutputKind},
+    };
+
+    // a quarter second at the rate and channels of the output, so nothing is resampled
+    const SAMPLES: usize = 44100 / 4 * 2;
+
+    // far from silence everywhere, where the song starts and ends is plain to see
+    fn sample(i: usize) -> i16 {
+        match i % 2 {
+            0 => 4000 + (i / 2 % 200) as i16 * 20,
+            _ => -4000 - (i / 2 % 150) as i16 * 20,
+        }
+    }
+
+    // an empty folder of its own for a test, the caches are shared by all of them
+    fn scratch(name: &str) -> PathBuf {
+        let root = env::temp_dir().join(format!("rustplayer-test-{}", process::id()));
+        // keep the loudness and length caches out of the real one
+        env::set_var("XDG_CACHE_HOME", root.join("cache"));
+        let dir = root.join(name);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    // `samples` of `sample` as a stereo 44.1 kHz file
+    fn tone(dir: &Path, name: &str, samples: usize) -> String {
+        let path = dir.join(name).to_string_lossy().to_string();
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 44100,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let mut writer = WavWriter::create(&path, spec).unwrap();
+        for i in 0..samples {
+            writer.write_sample(sample(i)).unwrap();
+        }
+        writer.finalize().unwrap();
+        path
+    }
+
+    // a player writing to `out`, with nothing but the samples of the files
+    fn wav_player(out: &str) -> MusicPlayer {
+        let output = output::open(&OutputKind::Wav(out.to_string()), None).unwrap();
+        let mut player = MusicPlayer::with_output(output);
+        player.ramp = Duration::ZERO;
+        player.replay_gain.mode = GainMode::Off;
+        player
+    }
+
+    fn local(path: &str) -> Media {
+        Media {
+            src: Source::Local(path.to_string()),
+        }
+    }
+
+    // tick for a while, as the app does
+    fn run(player: &mut MusicPlayer, time: Duration) {
+        let deadline = Instant::now() + time;
+        while Instant::now() < deadline {
+            player.tick().unwrap();
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    // tick until the list is over
+    fn play_out(player: &mut MusicPlayer) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while player.play_list.current.is_some() && Instant::now() < deadline {
+            player.tick().unwrap();
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(player.play_list.current.is_none(), "still playing");
+    }
+
+    // everything the output wrote, once the player is gone
+    fn written(player: MusicPlayer, out: &str) -> Vec<i16> {
+        // the output finalizes the file when it goes
+        drop(player);
+        WavReader::open(out)
+            .unwrap()
+            .into_samples()
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    #[test]
+    fn plays_a_file_to_wav() {
+        let dir = scratch("wav");
+        let input = tone(&dir, "in.wav", SAMPLES);
+        let out = dir.join("out.wav").to_string_lossy().to_string();
+        let mut player = wav_player(&out);
+        player.add_to_list(local(&input), true).unwrap();
+        play_out(&mut player);
+
+        let written = written(player, &out);
+        // silence from the mixer around the song
+        let start = written.iter().position(|s| s.abs() > 1000).unwrap();
+        let end = written.iter().rposition(|s| s.abs() > 1000).unwrap() + 1;
+        assert_eq!(start % 2, 0);
+        assert_eq!(end - start, SAMPLES);
+        for (i, s) in written[start..end].iter().enumerate() {
+            assert!((s - sample(i)).abs() <= 2, "sample {}: {}", i, s);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn adding_keeps_a_pause() {
+        let dir = scratch("pause");
+        let first = tone(&dir, "first.wav", SAMPLES * 8);
+        let second = tone(&dir, "second.wav", SAMPLES);
+        let out = dir.join("out.wav").to_string_lossy().to_string();
+        let mut player = wav_player(&out);
+        player.add_to_list(local(&first), true).unwrap();
+        run(&mut player, Duration::from_millis(200));
+        player.pause().unwrap();
+        run(&mut player, Duration::from_millis(50));
+
+        player.add_to_list(local(&second), false).unwrap();
+        assert!(player.is_paused());
+        assert!(player.play_list.lists[0].status == PlayStatus::Stopped);
+        let at = player.position.elapsed();
+        run(&mut player, Duration::from_millis(200));
+        assert_eq!(player.position.elapsed(), at);
+
+        // played at once, so up from the pause and audible
+        player.add_to_list(local(&second), true).unwrap();
+        assert!(player.is_playing());
+        assert!(player.play_list.lists[0].status == PlayStatus::Playing);
+        play_out(&mut player);
+
+        // the last thing written is the second file, at full volume
+        let written = written(player, &out);
+        let end = written.iter().rposition(|s| s.abs() > 1000).unwrap() + 1;
+        let start = written[..end]
+            .iter()
+            .rposition(|s| s.abs() <= 1000)
+            .unwrap()
+            + 1;
+        assert_eq!(end - start, SAMPLES);
+        for (i, s) in written[start..end].iter().enumerate() {
+            assert!((s - sample(i)).abs() <= 2, "sample {}: {}", i, s);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn drops_a_broken_file_once_probed() {
+        let dir = scratch("broken");
+        let song = tone(&dir, "song.wav", SAMPLES * 8);
+        let broken = dir.join("broken.mp3").to_string_lossy().to_string();
+        fs::write(&broken, "not a song").unwrap();
+        let out = dir.join("out.wav").to_string_lossy().to_string();
+        let mut player = wav_player(&out);
+        player.add_to_list(local(&song), true).unwrap();
+
+        // taken as it is, nothing is decoded on the way in
+        player.add_to_list(local(&broken), false).unwrap();
+        assert_eq!(player.play_list.lists.len(), 2);
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let failed = loop {
+            match player.tick() {
+                Err(err) => break err,
+                Ok(()) if Instant::now() < deadline => thread::sleep(Duration::from_millis(5)),
+                Ok(()) => panic!("never reported"),
+            }
+        };
+        assert!(failed.to_string().contains("broken.mp3"));
+        assert_eq!(player.play_list.lists.len(), 1);
+        assert_eq!(player.play_list.current, Some(0));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // lengths of the stretches louder than silence
+    fn runs(written: &[i16]) -> Vec<(usize, usize)> {
+        let mut runs = vec![];
+        let mut start = None;
+        for (i, s) in written.iter().enumerate() {
+            match (s.abs() > 1000, start) {
+                (true, None) => start = Some(i),
+                (false, Some(from)) => {
+                    runs.push((from, i - from));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(from) = start {
+            runs.push((from, written.len() - from));
+        }
+        runs
+    }
+
+    #[test]
+    fn item_gap_changes_only_the_queued_pass() {
+        let dir = scratch("item-gap");
+        let song = tone(&dir, "song.wav", SAMPLES * 2);
+        let out = dir.join("out.wav").to_string_lossy().to_string();
+        let mut player = wav_player(&out);
+        player.repetition = 2;
+        player.add_to_list(local(&song), true).unwrap();
+        run(&mut player, Duration::from_millis(250));
+        assert!(player.queued.is_some());
+
+        player.adjust_item_gap(0, 0.25).unwrap();
+        play_out(&mut player);
+
+        // the first pass went on where it was, the second came after the new gap
+        let runs = runs(&written(player, &out));
+        assert_eq!(runs.len(), 2, "{:?}", runs);
+        assert_eq!(runs[0].1, SAMPLES * 2);
+        assert_eq!(runs[1].1, SAMPLES * 2);
+        let gap = runs[1].0 - (runs[0].0 + runs[0].1);
+        assert!(gap.abs_diff(SAMPLES) < 2000, "gap of {} samples", gap);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn crossfade_is_bounded() {
+        let mut player = MusicPlayer::with_output(Box::new(output::null_output(1.0)));
+        for _ in 0..100 {
+            player.adjust_crossfade(0.5).unwrap();
+        }
+        assert_eq!(player.crossfade, MAX_CROSSFADE);
+        for _ in 0..100 {
+            player.adjust_crossfade(-0.5).unwrap();
+        }
+        assert_eq!(player.crossfade, MIN_CROSSFADE);
+    }
+
+    // samples of the songs in what was written
+    fn audible(written: &[i16]) -> usize {
+        written.iter().filter(|s| s.abs() > 1000).count()
+    }
+
+    #[test]
+    fn seeks_within_the_song() {
+        let dir = scratch("seek");
+        let song = tone(&dir, "song.wav", SAMPLES * 8);
+        let out = dir.join("out.wav").to_string_lossy().to_string();
+        let mut player = wav_player(&out);
+        player.add_to_list(local(&song), true).unwrap();
+        player.seek_to(Duration::from_millis(1500)).unwrap();
+        assert_eq!(player.current_time, Duration::from_millis(1500));
+        assert!(player.position.elapsed() >= Duration::from_millis(1500));
+        play_out(&mut player);
+
+        // next to nothing of the start, then the last half second to the end
+        let written = written(player, &out);
+        let heard = audible(&written) - SAMPLES * 2;
+        assert!(heard < 2000, "{} samples before the seek", heard);
+        let end = written.iter().rposition(|s| s.abs() > 1000).unwrap() + 1;
+        for (i, s) in written[end - SAMPLES..end].iter().enumerate() {
+            let expected = sample(SAMPLES * 7 + i);
+            assert!((s - expected).abs() <= 2, "sample {}: {}", i, s);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn seeks_by_within_bounds() {
+        let dir = scratch("seek-by");
+        let song = tone(&dir, "song.wav", SAMPLES * 8);
+        let mut player = MusicPlayer::with_output(Box::new(output::null_output(1.0)));
+        player.add_to_list(local(&song), true).unwrap();
+        // the length comes from the prober
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while player.play_list.lists[0].duration.is_none() && Instant::now() < deadline {
+            run(&mut player, Duration::from_millis(10));
+        }
+        assert_eq!(player.get_progress().1, 2.0);
+
+        player.pause().unwrap();
+        player.seek_by(1).unwrap();
+        assert!(player.is_paused());
+        let at = player.position.elapsed();
+        assert!(at >= Duration::from_secs(1) && at < Duration::from_millis(1300));
+        player.seek_by(-30).unwrap();
+        assert!(player.position.elapsed() < Duration::from_millis(100));
+        assert_eq!(player.current_time, Duration::ZERO);
+
+        // past the end is the end
+        player.resume().unwrap();
+        player.seek_by(30).unwrap();
+        assert_eq!(player.current_time, Duration::from_secs(2));
+        play_out(&mut player);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn loops_between_a_and_b() {
+        let dir = scratch("ab-loop");
+        let song = tone(&dir, "song.wav", SAMPLES * 8);
+        let out = dir.join("out.wav").to_string_lossy().to_string();
+        let mut player = wav_player(&out);
+        player.repetition = 2;
+        player.gap = 0.25;
+        player.add_to_list(local(&song), true).unwrap();
+        assert!(matches!(player.set_loop_b(), Err(PlayerError::NoLoopStart)));
+        player.seek_to(Duration::from_millis(500)).unwrap();
+        player.set_loop_a().unwrap();
+        player.seek_to(Duration::from_millis(1000)).unwrap();
+        player.set_loop_b().unwrap();
+        let ab = player.ab_loop.as_ref().unwrap();
+        assert_eq!(ab.remaining, 2);
+        assert!(ab.b.unwrap() - ab.a < Duration::from_millis(520));
+        play_out(&mut player);
+
+        // a to b twice with the gap between, on past b without one, then the second pass
+        let runs: Vec<usize> = runs(&written(player, &out))
+            .into_iter()
+            .map(|(_, len)| len)
+            .filter(|len| *len > 2000)
+            .collect();
+        assert_eq!(runs.len(), 3, "{:?}", runs);
+        assert!(runs[0].abs_diff(SAMPLES * 2) < 2000, "{:?}", runs);
+        assert!(runs[1].abs_diff(SAMPLES * 6) < 2000, "{:?}", runs);
+        assert_eq!(runs[2], SAMPLES * 8);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // a player on the null output with `count` songs of 4 s, the first one playing
+    fn queue(dir: &Path, count: usize) -> MusicPlayer {
+        let mut player = MusicPlayer::with_output(Box::new(output::null_output(1.0)));
+        for i in 0..count {
+            let song = tone(dir, &format!("{}.wav", i), SAMPLES * 16);
+            player.add_to_list(local(&song), false).unwrap();
+        }
+        assert_eq!(player.play_list.current, Some(0));
+        player
+    }
+
+    #[test]
+    fn repeats_one_or_all() {
+        let dir = scratch("repeat");
+        let mut player = queue(&dir, 3);
+        // the song again once it is over, skipping still moves on
+        player.switch_repeat().unwrap();
+        assert!(player.queue_mode == QueueMode::RepeatOne);
+        assert_eq!(player.following(false), Some(0));
+        player.next().unwrap();
+        assert_eq!(player.play_list.current, Some(1));
+
+        player.switch_repeat().unwrap();
+        assert!(player.queue_mode == QueueMode::RepeatAll);
+        player.next().unwrap();
+        player.next().unwrap();
+        assert_eq!(player.play_list.current, Some(0));
+        assert_eq!(player.preceding(), Some(2));
+
+        // the list is over after the last song, but still there
+        player.switch_repeat().unwrap();
+        assert!(player.queue_mode == QueueMode::Off);
+        player.play_at(2).unwrap();
+        player.next().unwrap();
+        assert_eq!(player.play_list.current, None);
+        assert_eq!(player.play_list.lists.len(), 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn shuffles_every_song_once() {
+        let dir = scratch("shuffle");
+        let mut player = queue(&dir, 5);
+        player.toggle_shuffle().unwrap();
+        let mut order = player.shuffle_order.clone();
+        assert_eq!(order[0], 0);
+        order.sort();
+        assert_eq!(order, [0, 1, 2, 3, 4]);
+
+        let mut played = vec![0];
+        for _ in 0..4 {
+            player.next().unwrap();
+            played.push(player.play_list.current.unwrap());
+        }
+        assert_eq!(played, player.shuffle_order);
+        player.next().unwrap();
+        assert_eq!(player.play_list.current, None);
+
+        // back in list order
+        player.play_at(1).unwrap();
+        player.toggle_shuffle().unwrap();
+        assert!(player.queue_mode == QueueMode::Off);
+        player.next().unwrap();
+        assert_eq!(player.play_list.current, Some(2));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn names(player: &MusicPlayer) -> Vec<&str> {
+        let items = player.play_list.lists.iter();
+        items.map(|item| item.name.as_str()).collect()
+    }
+
+    fn current_name(player: &MusicPlayer) -> &str {
+        player.playing_song().unwrap().name.as_str()
+    }
+
+    #[test]
+    fn edits_keep_the_current_song() {
+        let dir = scratch("edit");
+        let mut player = queue(&dir, 4);
+        player.play_at(2).unwrap();
+        player.move_item(2, true).unwrap();
+        assert_eq!(names(&player), ["0.wav", "2.wav", "1.wav", "3.wav"]);
+        assert_eq!(player.play_list.current, Some(1));
+        player.move_item(3, false).unwrap_err();
+        player.move_item(0, true).unwrap_err();
+
+        player.remove_at(0).unwrap();
+        assert_eq!(current_name(&player), "2.wav");
+        let extra = tone(&dir, "4.wav", SAMPLES);
+        player.insert_next(local(&extra)).unwrap();
+        assert_eq!(names(&player), ["2.wav", "4.wav", "1.wav", "3.wav"]);
+        player.remove_at(3).unwrap();
+        assert_eq!(current_name(&player), "2.wav");
+
+        // the current song goes, the one after it plays
+        player.remove_at(0).unwrap();
+        assert_eq!(names(&player), ["4.wav", "1.wav"]);
+        assert_eq!(current_name(&player), "4.wav");
+        assert!(player.is_playing());
+        player.remove_at(2).unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn edits_keep_the_shuffle_order() {
+        let dir = scratch("edit-shuffle");
+        let mut player = queue(&dir, 5);
+        player.toggle_shuffle().unwrap();
+        let order = |player: &MusicPlayer| -> Vec<String> {
+            let items = &player.play_list.lists;
+            let order = player.shuffle_order.iter();
+            order.map(|i| items[*i].name.clone()).collect()
+        };
+        let before = order(&player);
+        player.move_item(3, true).unwrap();
+        player.remove_at(1).unwrap();
+        let mut expected = before.clone();
+        expected.retain(|name| name != "1.wav");
+        assert_eq!(order(&player), expected);
+
+        // played right after the current song
+        let extra = tone(&dir, "5.wav", SAMPLES);
+        player.insert_next(local(&extra)).unwrap();
+        expected.insert(1, String::from("5.wav"));
+        assert_eq!(order(&player), expected);
+        player.next().unwrap();
+        assert_eq!(current_name(&player), "5.wav");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn goes_back_and_keeps_a_history() {
+        let dir = scratch("prev");
+        let mut player = queue(&dir, 3);
+        run(&mut player, Duration::from_millis(50));
+        // nothing before the first song, it starts over
+        player.prev().unwrap();
+        assert_eq!(player.play_list.current, Some(0));
+        run(&mut player, Duration::from_millis(50));
+        for _ in 0..2 {
+            player.next().unwrap();
+            run(&mut player, Duration::from_millis(50));
+        }
+        player.prev().unwrap();
+        assert_eq!(current_name(&player), "1.wav");
+        run(&mut player, Duration::from_millis(50));
+
+        // late in a song, back to its start
+        player.seek_to(Duration::from_millis(3500)).unwrap();
+        player.prev().unwrap();
+        assert_eq!(current_name(&player), "1.wav");
+        assert!(player.position.elapsed() < Duration::from_millis(100));
+        run(&mut player, Duration::from_millis(50));
+
+        let history = &player.history;
+        let played: Vec<&str> = history.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(
+            played,
+            ["0.wav", "0.wav", "1.wav", "2.wav", "1.wav", "1.wav"]
+        );
+        assert!(history.windows(2).all(|w| w[0].played_at <= w[1].played_at));
+        assert_eq!(player.play_list.lists.len(), 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn shadowing_gap_follows_the_pass() {
+        let dir = scratch("shadowing-gap");
+        let song = tone(&dir, "song.wav", SAMPLES);
+        let mut player = MusicPlayer::with_output(Box::new(output::null_output(1.0)));
+        player.add_to_list(local(&song), false).unwrap();
+        let item = &mut player.play_list.lists[0];
+        item.gap = 0.5;
+        item.duration = Some(Duration::from_secs(4));
+        player.gap_factor = 1.5;
+        assert_eq!(player.gap_after(), 0.5);
+
+        // factor × pass + the gap of the song
+        player.switch_transition().unwrap();
+        assert!(player.gap_mode == GapMode::Shadowing);
+        assert_eq!(player.gap_after(), 6.5);
+        let item = &player.play_list.lists[0];
+        assert_eq!(player.gap_for(item, Duration::from_secs(2)), 3.5);
+        // an A-B loop is the pass
+        player.ab_loop = Some(AbLoop {
+            a: Duration::from_secs(1),
+            b: Some(Duration::from_secs(3)),
+            remaining: 1,
+        });
+        assert_eq!(player.gap_after(), 3.5);
+        player.switch_transition().unwrap();
+        assert!(player.transition == Transition::Crossfade);
+        assert_eq!(player.gap_after(), 0.0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn shadowing_gap_is_as_long_as_the_song() {
+        let dir = scratch("shadowing");
+        let song = tone(&dir, "song.wav", SAMPLES * 2);
+        let out = dir.join("out.wav").to_string_lossy().to_string();
+        let mut player = wav_player(&out);
+        player.repetition = 2;
+        player.gap_mode = GapMode::Shadowing;
+        player.add_to_list(local(&song), true).unwrap();
+        play_out(&mut player);
+
+        let runs = runs(&written(player, &out));
+        assert_eq!(runs.len(), 2, "{:?}", runs);
+        let gap = runs[1].0 - (runs[0].0 + runs[0].1);
+        assert!(gap.abs_diff(SAMPLES * 2) < 2000, "gap of {} samples", gap);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,193 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+    time::Duration,
+};
+
+use super::{
+    media::{Media, Source},
+    stream::is_url,
+};
+
+pub const EXTENSIONS: [&str; 3] = ["m3u", "m3u8", "pls"];
+
+pub fn is_playlist(path: &str) -> bool {
+    match Path::new(path).extension() {
+        Some(ext) => EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str()),
+        None => false,
+    }
+}
+
+/// Entries of an M3U, M3U8 or PLS file, paths relative to the file's folder.
+pub fn read(path: &str) -> io::Result<Vec<Media>> {
+    let mut bytes = fs::read(path)?;
+    // a byte order mark, even in front of Latin-1 text
+    if bytes.starts_with(b"\xef\xbb\xbf") {
+        bytes.drain(..3);
+    }
+    // .m3u is often Latin-1
+    let text = match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(err) => err.into_bytes().iter().map(|b| *b as char).collect(),
+    };
+    let locations = match path.to_lowercase().ends_with(".pls") {
+        true => pls_entries(text.as_str()),
+        false => m3u_entries(text.as_str()),
+    };
+    let dir = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+    Ok(locations
+        .into_iter()
+        .map(|location| Media {
+            src: resolve(dir, location),
+        })
+        .collect())
+}
+
+// every line that is not blank or a comment
+fn m3u_entries(text: &str) -> Vec<&str> {
+    text.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect()
+}
+
+// `FileN=` keys, in the order of N
+fn pls_entries(text: &str) -> Vec<&str> {
+    let mut entries = vec![];
+    for line in text.lines() {
+        if let Some((key, value)) = line.trim().split_once('=') {
+            let number = key
+                .trim()
+                .strip_prefix("File")
+                .and_then(|n| n.parse::<u32>().ok());
+            if let Some(number) = number {
+                entries.push((number, value.trim()));
+            }
+        }
+    }
+    entries.sort_by_key(|(number, _)| *number);
+    entries.into_iter().map(|(_, location)| location).collect()
+}
+
+fn resolve(dir: &Path, location: &str) -> Source {
+    if is_url(location) {
+        return Source::Http(location.to_string());
+    }
+    let location = location.strip_prefix("file://").unwrap_or(location);
+    let path = dir.join(location);
+    Source::Local(path.to_string_lossy().to_string())
+}
+
+/// One line of a saved play list.
+pub struct Entry {
+    pub location: String,
+    pub duration: Option<Duration>,
+    pub title: String,
+}
+
+/// Save as extended M3U in UTF-8.
+pub fn write_m3u8(path: &str, entries: &[Entry]) -> io::Result<()> {
+    let mut file = fs::File::create(path)?;
+    writeln!(file, "#EXTM3U")?;
+    for entry in entries {
+        // -1 for an unknown length
+        let secs = entry
+            .duration
+            .map_or(-1, |duration| duration.as_secs() as i64);
+        let title = entry.title.replace(['\r', '\n'], " ");
+        writeln!(file, "#EXTINF:{},{}", secs, title)?;
+        writeln!(file, "{}", entry.location)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process, time::Duration};
+
+    use super::{read, write_m3u8, Entry, Source};
+
+    // the location of every entry, urls marked
+    fn locations(path: &str) -> Vec<String> {
+        read(path)
+            .unwrap()
+            .into_iter()
+            .map(|media| match media.src {
+                Source::Local(path) => path,
+                Source::Http(url) => format!("url {}", url),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reads_what_it_writes() {
+        let dir = env::temp_dir().join(format!("rustplayer-playlist-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let local = |name: &str| dir.join(name).to_string_lossy().to_string();
+
+        // written and read back, the -1 of an unknown length is skipped
+        let saved = dir.join("saved.m3u8").to_string_lossy().to_string();
+        let entries = [
+            Entry {
+                location: local("a.mp3"),
+                duration: Some(Duration::from_secs(61)),
+                title: "Artist\n- A".to_string(),
+            },
+            Entry {
+                location: "http://radio.example/live".to_string(),
+                duration: None,
+                title: "Radio".to_string(),
+            },
+        ];
+        write_m3u8(&saved, &entries).unwrap();
+        let text = fs::read_to_string(&saved).unwrap();
+        assert!(text.contains("#EXTINF:61,Artist - A\n"));
+        assert!(text.contains("#EXTINF:-1,Radio\n"));
+        assert_eq!(
+            locations(&saved),
+            [local("a.mp3"), "url http://radio.example/live".to_string()]
+        );
+
+        // a BOM, file:// and a Latin-1 name relative to the list
+        let m3u = dir.join("list.m3u");
+        let mut bytes = "\u{feff}#EXTM3U\nfile://b.mp3\n\n".as_bytes().to_vec();
+        bytes.extend(b"caf\xe9.mp3\n");
+        fs::write(&m3u, bytes).unwrap();
+        assert_eq!(
+            locations(&m3u.to_string_lossy()),
+            [local("b.mp3"), local("caf\u{e9}.mp3")]
+        );
+
+        // FileN= keys out of order
+        let pls = dir.join("list.pls");
+        fs::write(
+            &pls,
+            "[playlist]\nFile2=http://radio.example/two\nTitle2=Two\nFile1=one.mp3\nNumberOfEntries=2\n",
+        )
+        .unwrap();
+        assert_eq!(
+            locations(&pls.to_string_lossy()),
+            [local("one.mp3"), "url http://radio.example/two".to_string()]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
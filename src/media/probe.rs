@@ -0,0 +1,185 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    fs::File,
+    path::Path,
+    sync::mpsc::{self, Receiver, Sender},
+    time::Duration,
+};
+
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+use symphonia::core::{
+    formats::{FormatOptions, FormatReader},
+    io::MediaSourceStream,
+    meta::MetadataOptions,
+    probe::Hint,
+    units::TimeBase,
+};
+
+use super::{
+    cache::{FileCache, Priority, Worker},
+    decoder,
+    loudness::GainTags,
+    player::PlayerError,
+};
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Length {
+    // milliseconds
+    duration: u64,
+}
+
+/// Whether files play at all, their lengths and ReplayGain tags, found on the
+/// [`Worker`]. Lengths are kept in `<cache dir>/rustplayer/durations.toml`.
+pub struct Prober {
+    cache: FileCache<Length>,
+    worker: Worker,
+    sender: Sender<(String, Probed)>,
+    results: Receiver<(String, Probed)>,
+}
+
+/// What a file needs before it can play, or why it can not.
+pub type Probed = Result<FileInfo, PlayerError>;
+
+pub struct FileInfo {
+    // None when it could not be found
+    pub duration: Option<Duration>,
+    pub gain_tags: GainTags,
+}
+
+impl Prober {
+    pub fn new(worker: &Worker) -> Self {
+        let (sender, results) = mpsc::channel();
+        Self {
+            cache: FileCache::new("durations", worker),
+            worker: worker.clone(),
+            sender,
+            results,
+        }
+    }
+
+    /// cached length of `path`, if the file has not changed since
+    pub fn get(&self, path: &str) -> Option<Duration> {
+        self.cache.get(path).map(|length| length.to_duration())
+    }
+
+    /// check that `path` plays and read what it needs in the background, it comes out of `poll`
+    pub fn request(&self, path: &str) {
+        let (cache, sender, path) = (self.cache.clone(), self.sender.clone(), path.to_string());
+        // the play list shows it
+        self.worker.run(Priority::High, move || {
+            let probed = decoder::open(path.as_str()).map(|_| FileInfo {
+                duration: length(&cache, path.as_str()),
+                gain_tags: GainTags::read(path.as_str()),
+            });
+            let _ = sender.send((path, probed));
+        });
+    }
+
+    pub fn poll(&self) -> Option<(String, Probed)> {
+        self.results.try_recv().ok()
+    }
+}
+
+impl Length {
+    fn to_duration(&self) -> Duration {
+        Duration::from_millis(self.duration)
+    }
+}
+
+// cached, or found and cached
+fn length(cache: &FileCache<Length>, path: &str) -> Option<Duration> {
+    if let Some(length) = cache.get(path) {
+        return Some(length.to_duration());
+    }
+    let duration = probe(path)?;
+    let length = Length {
+        duration: duration.as_millis() as u64,
+    };
+    cache.insert(path, length);
+    Some(duration)
+}
+
+// the header if it tells, else every packet, else every sample
+fn probe(path: &str) -> Option<Duration> {
+    if let Some(mut reader) = open_container(path) {
+        if let Some(duration) = header_duration(reader.as_ref()) {
+            return Some(duration);
+        }
+        if let Some(duration) = scan_packets(reader.as_mut()) {
+            return Some(duration);
+        }
+    }
+    count_samples(path)
+}
+
+fn open_container(path: &str) -> Option<Box<dyn FormatReader>> {
+    let mss = MediaSourceStream::new(Box::new(File::open(path).ok()?), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = Path::new(path).extension() {
+        hint.with_extension(ext.to_string_lossy().as_ref());
+    }
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .ok()?;
+    Some(probed.format)
+}
+
+fn time_base(reader: &dyn FormatReader) -> Option<TimeBase> {
+    let params = &reader.default_track()?.codec_params;
+    params
+        .time_base
+        .or_else(|| params.sample_rate.map(|rate| TimeBase::new(1, rate)))
+}
+
+fn to_duration(time_base: TimeBase, ts: u64) -> Duration {
+    let time = time_base.calc_time(ts);
+    Duration::from_secs(time.seconds) + Duration::from_secs_f64(time.frac)
+}
+
+fn header_duration(reader: &dyn FormatReader) -> Option<Duration> {
+    let frames = reader.default_track()?.codec_params.n_frames?;
+    Some(to_duration(time_base(reader)?, frames))
+}
+
+// demux without decoding, the end of the last packet is the length
+fn scan_packets(reader: &mut dyn FormatReader) -> Option<Duration> {
+    let track = reader.default_track()?.id;
+    let time_base = time_base(reader)?;
+    let mut end = None;
+    while let Ok(packet) = reader.next_packet() {
+        if packet.track_id() == track {
+            end = Some(packet.ts() + packet.dur());
+        }
+    }
+    end.map(|end| to_duration(time_base, end))
+}
+
+fn count_samples(path: &str) -> Option<Duration> {
+    let decoder = decoder::open(path).ok()?;
+    let frames = decoder.channels().max(1) as u64;
+    let rate = decoder.sample_rate() as f64;
+    let samples = decoder.count() as u64;
+    Some(Duration::from_secs_f64((samples / frames) as f64 / rate))
+}
@@ -0,0 +1,287 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    sync::mpsc::{self, Receiver, Sender},
+    time::Duration,
+};
+
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    cache::{FileCache, Priority, Worker},
+    decoder,
+};
+
+// level is measured over 10 ms windows
+const WINDOW: Duration = Duration::from_millis(10);
+
+/// When a pause is long and quiet enough to split a recording at.
+#[derive(Clone, Copy, PartialEq)]
+pub struct SegmentSettings {
+    // dBFS below which a window counts as silence
+    pub threshold: f32,
+    // seconds of silence that end a sentence
+    pub min_silence: f32,
+    // shorter segments are merged into the one before
+    pub min_segment: f32,
+}
+
+impl Default for SegmentSettings {
+    fn default() -> Self {
+        Self {
+            threshold: -40.0,
+            min_silence: 0.4,
+            min_segment: 1.0,
+        }
+    }
+}
+
+/// start and end of every segment, covering the whole file
+pub type Segments = Vec<(Duration, Duration)>;
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Split {
+    threshold: f32,
+    min_silence: f32,
+    min_segment: f32,
+    // milliseconds, so lengths come back exactly
+    segments: Vec<(u64, u64)>,
+}
+
+impl Split {
+    fn new(segments: &Segments, settings: &SegmentSettings) -> Self {
+        Self {
+            threshold: settings.threshold,
+            min_silence: settings.min_silence,
+            min_segment: settings.min_segment,
+            segments: segments
+                .iter()
+                .map(|(from, to)| (from.as_millis() as u64, to.as_millis() as u64))
+                .collect(),
+        }
+    }
+
+    fn matches(&self, settings: &SegmentSettings) -> bool {
+        self.threshold == settings.threshold
+            && self.min_silence == settings.min_silence
+            && self.min_segment == settings.min_segment
+    }
+
+    fn segments(&self) -> Segments {
+        self.segments
+            .iter()
+            .map(|(from, to)| (Duration::from_millis(*from), Duration::from_millis(*to)))
+            .collect()
+    }
+}
+
+/// Splits files at their silences on the [`Worker`], results are kept in
+/// `<cache dir>/rustplayer/segments.toml`.
+pub struct Segmenter {
+    cache: FileCache<Split>,
+    worker: Worker,
+    sender: Sender<(String, Option<Segments>)>,
+    results: Receiver<(String, Option<Segments>)>,
+}
+
+impl Segmenter {
+    pub fn new(worker: &Worker) -> Self {
+        let (sender, results) = mpsc::channel();
+        Self {
+            cache: FileCache::new("segments", worker),
+            worker: worker.clone(),
+            sender,
+            results,
+        }
+    }
+
+    /// cached segments of `path`, if they were made with the same settings
+    pub fn get(&self, path: &str, settings: &SegmentSettings) -> Option<Segments> {
+        match self.cache.get(path) {
+            Some(split) if split.matches(settings) => Some(split.segments()),
+            _ => None,
+        }
+    }
+
+    /// analyze `path` in the background, the result comes out of `poll`
+    pub fn request(&self, path: &str, settings: SegmentSettings) {
+        let (cache, sender, path) = (self.cache.clone(), self.sender.clone(), path.to_string());
+        self.worker.run(Priority::Normal, move || {
+            let segments = analyze(path.as_str(), &settings);
+            if let Some(segments) = &segments {
+                cache.insert(path.as_str(), Split::new(segments, &settings));
+            }
+            let _ = sender.send((path, segments));
+        });
+    }
+
+    // None when the file could not be decoded
+    pub fn poll(&self) -> Option<(String, Option<Segments>)> {
+        self.results.try_recv().ok()
+    }
+}
+
+// cut at the middle of every long enough pause inside the file
+fn analyze(path: &str, settings: &SegmentSettings) -> Option<Segments> {
+    let decoder = decoder::open(path).ok()?;
+    let channels = decoder.channels().max(1) as usize;
+    let len = (decoder.sample_rate() as u128 * WINDOW.as_millis() / 1000) as usize * channels;
+    // mean square of full scale at the threshold
+    let floor = 10f64.powf(settings.threshold as f64 / 10.0);
+    let mut silent = vec![];
+    let mut sum = 0.0f64;
+    let mut count = 0;
+    for sample in decoder {
+        let sample = sample as f64 / i16::MAX as f64;
+        sum += sample * sample;
+        count += 1;
+        if count == len {
+            silent.push(sum / len as f64 <= floor);
+            sum = 0.0;
+            count = 0;
+        }
+    }
+    if count > 0 {
+        silent.push(sum / count as f64 <= floor);
+    }
+    let min_run = (settings.min_silence / WINDOW.as_secs_f32())
+        .ceil()
+        .max(1.0) as usize;
+    let mut cuts = vec![0];
+    let mut run_start = None;
+    for (i, is_silent) in silent.iter().enumerate() {
+        match (is_silent, run_start) {
+            (true, None) => run_start = Some(i),
+            (false, Some(start)) => {
+                // leading silence stays with the first segment
+                if i - start >= min_run && start > 0 {
+                    cuts.push((start + i) / 2);
+                }
+                run_start = None;
+            }
+            _ => {}
+        }
+    }
+    cuts.push(silent.len());
+    let min_len = (settings.min_segment / WINDOW.as_secs_f32()).ceil() as usize;
+    let mut bounds: Vec<usize> = vec![0];
+    for cut in cuts.into_iter().skip(1) {
+        let last = *bounds.last().unwrap();
+        if cut - last < min_len && bounds.len() > 1 {
+            // too short, grow the previous segment instead
+            bounds.pop();
+        }
+        bounds.push(cut);
+    }
+    if bounds.len() > 2 && bounds[1] - bounds[0] < min_len {
+        // a short first segment joins the second
+        bounds.remove(1);
+    }
+    let at = |i: usize| WINDOW * i as u32;
+    Some(bounds.windows(2).map(|w| (at(w[0]), at(w[1]))).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, f32::consts::PI, fs, process};
+
+    use hound::{SampleFormat, WavSpec, WavWriter};
+
+    use super::{analyze, SegmentSettings};
+
+    // mono 44.1 kHz file of (seconds, is_tone) parts
+    fn speech(name: &str, parts: &[(f32, bool)]) -> String {
+        let dir = env::temp_dir().join(format!("rustplayer-test-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name).to_string_lossy().to_string();
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: 44100,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let mut writer = WavWriter::create(&path, spec).unwrap();
+        for (secs, is_tone) in parts {
+            for i in 0..(secs * 44100.0).round() as usize {
+                let level = if *is_tone { 0.5 } else { 0.0 };
+                let sample = (2.0 * PI * 440.0 * i as f32 / 44100.0).sin() * level;
+                writer
+                    .write_sample((sample * i16::MAX as f32) as i16)
+                    .unwrap();
+            }
+        }
+        writer.finalize().unwrap();
+        path
+    }
+
+    fn segments(name: &str, parts: &[(f32, bool)]) -> Vec<(u128, u128)> {
+        analyze(&speech(name, parts), &SegmentSettings::default())
+            .unwrap()
+            .iter()
+            .map(|(from, to)| (from.as_millis(), to.as_millis()))
+            .collect()
+    }
+
+    #[test]
+    fn no_silence_is_one_segment() {
+        assert_eq!(segments("segment-tone.wav", &[(2.0, true)]), [(0, 2000)]);
+    }
+
+    #[test]
+    fn edges_stay_with_their_segment() {
+        let parts = [(0.5, false), (2.0, true), (0.6, false)];
+        assert_eq!(segments("segment-edges.wav", &parts), [(0, 3100)]);
+    }
+
+    #[test]
+    fn short_pauses_do_not_split() {
+        let parts = [(1.5, true), (0.3, false), (1.5, true)];
+        assert_eq!(segments("segment-short.wav", &parts), [(0, 3300)]);
+    }
+
+    #[test]
+    fn splits_in_the_middle_of_a_pause() {
+        let parts = [(1.5, true), (0.6, false), (1.5, true)];
+        assert_eq!(
+            segments("segment-pause.wav", &parts),
+            [(0, 1800), (1800, 3600)]
+        );
+    }
+
+    #[test]
+    fn short_segments_are_merged() {
+        // the middle sentence is shorter than min_segment and joins the first
+        let parts = [
+            (1.5, true),
+            (0.6, false),
+            (0.2, true),
+            (0.6, false),
+            (1.5, true),
+        ];
+        assert_eq!(
+            segments("segment-merge.wav", &parts),
+            [(0, 2600), (2600, 4400)]
+        );
+    }
+
+    #[test]
+    fn unreadable_files_give_none() {
+        assert!(analyze("/nonexistent.wav", &SegmentSettings::default()).is_none());
+    }
+}
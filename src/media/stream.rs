@@ -0,0 +1,622 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    collections::VecDeque,
+    io::{self, Read},
+    sync::{
+        mpsc::{self, TryRecvError, TrySendError},
+        Arc, Condvar, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use chrono::{DateTime, Local};
+use rodio::Source;
+use symphonia::core::io::ReadOnlySource;
+
+use super::{
+    decoder::{decode_error, MediaDecoder},
+    player::PlayerError,
+};
+
+// bytes held between the network and the decoder
+const CAPACITY: usize = 512 * 1024;
+// decoded chunks, a packet each, held between the decoder and the output
+const CHUNKS: usize = 256;
+// sound buffered before a station starts playing
+const PREFILL: Duration = Duration::from_millis(500);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+// a station that sends nothing for this long has ended
+const STALL_TIMEOUT: Duration = Duration::from_secs(15);
+// titles remembered per station
+const TITLE_LOG: usize = 50;
+
+pub fn is_url(path: &str) -> bool {
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
+#[derive(Default)]
+struct MetaState {
+    station: Option<String>,
+    title: Option<String>,
+    // oldest first
+    log: VecDeque<(DateTime<Local>, String)>,
+}
+
+/// ICY metadata of a station, written by its streams and read by the UI.
+#[derive(Clone, Default)]
+pub struct StreamMeta(Arc<Mutex<MetaState>>);
+
+impl StreamMeta {
+    /// name the server gives the station
+    pub fn station(&self) -> Option<String> {
+        self.0.lock().unwrap().station.clone()
+    }
+
+    /// what is on air now
+    pub fn title(&self) -> Option<String> {
+        self.0.lock().unwrap().title.clone()
+    }
+
+    /// titles played recently with when they started, oldest first
+    pub fn log(&self) -> Vec<(DateTime<Local>, String)> {
+        self.0.lock().unwrap().log.iter().cloned().collect()
+    }
+
+    fn set_station(&self, station: String) {
+        self.0.lock().unwrap().station = Some(station);
+    }
+
+    fn set_title(&self, title: String) {
+        let mut state = self.0.lock().unwrap();
+        // the same block repeats until the title changes
+        if state.title.as_ref() == Some(&title) {
+            return;
+        }
+        state.log.push_back((Local::now(), title.clone()));
+        if state.log.len() > TITLE_LOG {
+            state.log.pop_front();
+        }
+        state.title = Some(title);
+    }
+}
+
+enum IcyState {
+    // audio bytes left before the next metadata block
+    Audio(usize),
+    Length,
+    // metadata bytes left in this block
+    Meta(usize),
+}
+
+// splits the metadata blocks a server sends every `interval` bytes out of the audio
+struct IcyDemux {
+    interval: usize,
+    state: IcyState,
+    block: Vec<u8>,
+}
+
+impl IcyDemux {
+    fn new(interval: usize) -> Self {
+        Self {
+            interval,
+            state: IcyState::Audio(interval),
+            block: vec![],
+        }
+    }
+
+    // the audio of `chunk`, titles of the blocks completed in it go to `meta`
+    fn feed(&mut self, chunk: &[u8], meta: &StreamMeta) -> Vec<u8> {
+        let mut audio = Vec::with_capacity(chunk.len());
+        let mut rest = chunk;
+        while !rest.is_empty() {
+            match self.state {
+                IcyState::Audio(left) => {
+                    let len = left.min(rest.len());
+                    audio.extend_from_slice(&rest[..len]);
+                    rest = &rest[len..];
+                    self.state = match left - len {
+                        0 => IcyState::Length,
+                        left => IcyState::Audio(left),
+                    };
+                }
+                IcyState::Length => {
+                    // in units of 16 bytes
+                    let len = rest[0] as usize * 16;
+                    rest = &rest[1..];
+                    self.block.clear();
+                    self.state = match len {
+                        0 => IcyState::Audio(self.interval),
+                        len => IcyState::Meta(len),
+                    };
+                }
+                IcyState::Meta(left) => {
+                    let len = left.min(rest.len());
+                    self.block.extend_from_slice(&rest[..len]);
+                    rest = &rest[len..];
+                    self.state = match left - len {
+                        0 => {
+                            if let Some(title) = stream_title(&self.block) {
+                                meta.set_title(title);
+                            }
+                            IcyState::Audio(self.interval)
+                        }
+                        left => IcyState::Meta(left),
+                    };
+                }
+            }
+        }
+        audio
+    }
+}
+
+// `StreamTitle='Artist - Song';StreamUrl='';` padded with zeros
+fn stream_title(block: &[u8]) -> Option<String> {
+    // UTF-8 mostly, older stations send Latin-1
+    let text: String = match std::str::from_utf8(block) {
+        Ok(text) => text.to_string(),
+        Err(_) => block.iter().map(|b| *b as char).collect(),
+    };
+    let start = text.find("StreamTitle='")? + "StreamTitle='".len();
+    let len = text[start..].find("';").unwrap_or(text.len() - start);
+    let title = text[start..start + len].trim();
+    match title.is_empty() {
+        true => None,
+        false => Some(title.to_string()),
+    }
+}
+
+#[derive(Default)]
+struct Buffer {
+    data: VecDeque<u8>,
+    // the response has ended, with an error if it broke off
+    done: bool,
+    error: Option<String>,
+    // the reader is gone, stop downloading
+    closed: bool,
+}
+
+#[derive(Default)]
+struct Shared {
+    buffer: Mutex<Buffer>,
+    changed: Condvar,
+}
+
+impl Shared {
+    // wait for room, false once nobody reads anymore
+    fn push(&self, chunk: &[u8]) -> bool {
+        let mut buffer = self.buffer.lock().unwrap();
+        while buffer.data.len() >= CAPACITY && !buffer.closed {
+            buffer = self.changed.wait(buffer).unwrap();
+        }
+        if buffer.closed {
+            return false;
+        }
+        buffer.data.extend(chunk);
+        self.changed.notify_all();
+        true
+    }
+
+    fn finish(&self, error: Option<String>) {
+        let mut buffer = self.buffer.lock().unwrap();
+        buffer.done = true;
+        buffer.error = error;
+        self.changed.notify_all();
+    }
+
+    fn pull(&self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut buffer = self.buffer.lock().unwrap();
+        while buffer.data.is_empty() && !buffer.done {
+            let (next, timeout) = self.changed.wait_timeout(buffer, STALL_TIMEOUT).unwrap();
+            buffer = next;
+            if timeout.timed_out() && buffer.data.is_empty() {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "stream stalled"));
+            }
+        }
+        if buffer.data.is_empty() {
+            return match &buffer.error {
+                Some(err) => Err(io::Error::other(err.clone())),
+                None => Ok(0),
+            };
+        }
+        let len = buf.len().min(buffer.data.len());
+        for (dst, src) in buf.iter_mut().zip(buffer.data.drain(..len)) {
+            *dst = src;
+        }
+        self.changed.notify_all();
+        Ok(len)
+    }
+
+    fn close(&self) {
+        self.buffer.lock().unwrap().closed = true;
+        self.changed.notify_all();
+    }
+}
+
+/// Body of an HTTP(S) response, downloaded on a worker thread into a bounded
+/// buffer.
+pub struct HttpStream {
+    shared: Arc<Shared>,
+}
+
+impl HttpStream {
+    /// connect to `url`, returns once the response headers are in
+    pub fn open(url: &str, meta: StreamMeta) -> Result<Self, String> {
+        let shared = Arc::new(Shared::default());
+        let (ready, connected) = mpsc::channel();
+        let worker = shared.clone();
+        let url = url.to_string();
+        thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build();
+            match runtime {
+                Ok(runtime) => runtime.block_on(download(url, worker, meta, ready)),
+                Err(err) => {
+                    let _ = ready.send(Err(err.to_string()));
+                }
+            }
+        });
+        match connected.recv() {
+            Ok(Ok(())) => Ok(Self { shared }),
+            Ok(Err(err)) => Err(err),
+            Err(_) => Err("connection closed".to_string()),
+        }
+    }
+}
+
+async fn download(
+    url: String,
+    shared: Arc<Shared>,
+    meta: StreamMeta,
+    ready: mpsc::Sender<Result<(), String>>,
+) {
+    let client = match reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .build()
+    {
+        Ok(client) => client,
+        Err(err) => {
+            let _ = ready.send(Err(err.to_string()));
+            return;
+        }
+    };
+    let response = client
+        .get(url.as_str())
+        // ask for the titles, shoutcast and icecast interleave them with the audio
+        .header("Icy-MetaData", "1")
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    let mut response = match response {
+        Ok(response) => response,
+        Err(err) => {
+            let _ = ready.send(Err(err.to_string()));
+            return;
+        }
+    };
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim().to_string())
+    };
+    if let Some(station) = header("icy-name").filter(|name| !name.is_empty()) {
+        meta.set_station(station);
+    }
+    let mut demux = header("icy-metaint")
+        .and_then(|interval| interval.parse::<usize>().ok())
+        .filter(|interval| *interval > 0)
+        .map(IcyDemux::new);
+    let _ = ready.send(Ok(()));
+    loop {
+        match response.chunk().await {
+            Ok(Some(chunk)) => {
+                let pushed = match &mut demux {
+                    Some(demux) => shared.push(&demux.feed(&chunk, &meta)),
+                    None => shared.push(&chunk),
+                };
+                if !pushed {
+                    return;
+                }
+            }
+            Ok(None) => return shared.finish(None),
+            Err(err) => return shared.finish(Some(err.to_string())),
+        }
+    }
+}
+
+impl Read for HttpStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.shared.pull(buf)
+    }
+}
+
+impl Drop for HttpStream {
+    fn drop(&mut self) {
+        self.shared.close();
+    }
+}
+
+/// Station being connected to on a worker thread.
+pub struct Connecting(mpsc::Receiver<Result<LiveSource, PlayerError>>);
+
+impl Connecting {
+    /// the station once it has sound to play, None while it is not there yet
+    pub fn poll(&self) -> Option<Result<LiveSource, PlayerError>> {
+        self.0.try_recv().ok()
+    }
+}
+
+/// connect to a station and decode it in the background, its titles go to `meta`
+pub fn connect(url: &str, meta: StreamMeta) -> Connecting {
+    let (ready, connected) = mpsc::channel();
+    let url = url.to_string();
+    thread::spawn(move || decode(url, meta, ready));
+    Connecting(connected)
+}
+
+// the source is handed over once PREFILL is decoded, the rest is fed to it as it
+// comes in until the station ends or the source is dropped
+fn decode(url: String, meta: StreamMeta, ready: mpsc::Sender<Result<LiveSource, PlayerError>>) {
+    let stream = match HttpStream::open(url.as_str(), meta) {
+        Ok(stream) => stream,
+        Err(err) => {
+            let _ = ready.send(Err(PlayerError::Stream(url, err)));
+            return;
+        }
+    };
+    let mut decoder = match MediaDecoder::new(Box::new(ReadOnlySource::new(stream)), None) {
+        Ok(decoder) => decoder,
+        Err(err) => {
+            let _ = ready.send(Err(decode_error(url.as_str(), err)));
+            return;
+        }
+    };
+    let channels = decoder.channels();
+    let sample_rate = decoder.sample_rate();
+    let prefill = (PREFILL.as_secs_f64() * sample_rate as f64) as usize * channels as usize;
+    let (sender, chunks) = mpsc::sync_channel(CHUNKS);
+    let mut pending = Some(LiveSource::new(chunks, channels, sample_rate));
+    let mut buffered = 0;
+    while let Some(chunk) = decoder.next_chunk() {
+        if decoder.channels() != channels || decoder.sample_rate() != sample_rate {
+            // the station switched formats, end it like a file
+            break;
+        }
+        let chunk = match pending.take() {
+            Some(source) => {
+                buffered += chunk.len();
+                let full = match sender.try_send(chunk) {
+                    Ok(()) => None,
+                    Err(TrySendError::Full(chunk)) => Some(chunk),
+                    Err(TrySendError::Disconnected(_)) => return,
+                };
+                if buffered < prefill && full.is_none() {
+                    pending = Some(source);
+                    continue;
+                }
+                if ready.send(Ok(source)).is_err() {
+                    // nobody waits for this station anymore
+                    return;
+                }
+                match full {
+                    Some(chunk) => chunk,
+                    None => continue,
+                }
+            }
+            None => chunk,
+        };
+        if sender.send(chunk).is_err() {
+            return;
+        }
+    }
+    // ended before it filled the buffer, play what there is
+    if let Some(source) = pending {
+        let _ = ready.send(Ok(source));
+    }
+}
+
+/// Decoded sound of a station. Plays silence while the network falls behind
+/// instead of holding up the output, and ends with the station.
+pub struct LiveSource {
+    chunks: mpsc::Receiver<Vec<i16>>,
+    chunk: Vec<i16>,
+    pos: usize,
+    // zeros left of the frame standing in for a late chunk
+    silence: u16,
+    channels: u16,
+    sample_rate: u32,
+}
+
+impl LiveSource {
+    fn new(chunks: mpsc::Receiver<Vec<i16>>, channels: u16, sample_rate: u32) -> Self {
+        Self {
+            chunks,
+            chunk: vec![],
+            pos: 0,
+            silence: 0,
+            channels,
+            sample_rate,
+        }
+    }
+}
+
+impl Iterator for LiveSource {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if self.silence > 0 {
+            self.silence -= 1;
+            return Some(0);
+        }
+        if self.pos >= self.chunk.len() {
+            match self.chunks.try_recv() {
+                Ok(chunk) => {
+                    self.chunk = chunk;
+                    self.pos = 0;
+                }
+                Err(TryRecvError::Empty) => {
+                    // a whole frame, so the channels stay in step
+                    self.silence = self.channels - 1;
+                    return Some(0);
+                }
+                Err(TryRecvError::Disconnected) => return None,
+            }
+        }
+        self.pos += 1;
+        Some(self.chunk[self.pos - 1])
+    }
+}
+
+impl Source for LiveSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+        time::Duration,
+    };
+
+    use super::{stream_title, HttpStream, IcyDemux, StreamMeta};
+
+    const TITLE: &str = "Artist - Song";
+
+    fn body() -> Vec<u8> {
+        (0..20_000u32).map(|i| (i * 31 % 251) as u8).collect()
+    }
+
+    // `body` with a metadata block after every `interval` bytes, as a station sends it
+    fn interleave(body: &[u8], interval: usize) -> Vec<u8> {
+        let mut text = format!("StreamTitle='{}';StreamUrl='';", TITLE).into_bytes();
+        text.resize(text.len().div_ceil(16) * 16, 0);
+        let mut out = vec![];
+        for chunk in body.chunks(interval) {
+            out.extend_from_slice(chunk);
+            if chunk.len() == interval {
+                out.push((text.len() / 16) as u8);
+                out.extend_from_slice(&text);
+            }
+        }
+        out
+    }
+
+    // answers one request with `body` in small writes, returns its url
+    fn serve(body: Vec<u8>, interval: Option<usize>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            let (mut socket, _) = listener.accept().unwrap();
+            let mut request = vec![];
+            let mut byte = [0u8; 1];
+            while !request.ends_with(b"\r\n\r\n") {
+                if socket.read(&mut byte).unwrap() == 0 {
+                    return;
+                }
+                request.push(byte[0]);
+            }
+            let mut head =
+                "HTTP/1.0 200 OK\r\nContent-Type: audio/mpeg\r\nicy-name: Test FM\r\n".to_string();
+            let payload = match interval {
+                Some(interval) => {
+                    head += &format!("icy-metaint: {}\r\n", interval);
+                    interleave(&body, interval)
+                }
+                None => body,
+            };
+            head += "\r\n";
+            socket.write_all(head.as_bytes()).unwrap();
+            // block boundaries fall inside the writes
+            for chunk in payload.chunks(700) {
+                if socket.write_all(chunk).is_err() {
+                    return;
+                }
+                thread::sleep(Duration::from_millis(1));
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn reads_the_body() {
+        let meta = StreamMeta::default();
+        let mut stream = HttpStream::open(serve(body(), None).as_str(), meta.clone()).unwrap();
+        let mut read = vec![];
+        stream.read_to_end(&mut read).unwrap();
+        assert_eq!(read, body());
+        assert_eq!(meta.station().as_deref(), Some("Test FM"));
+        assert_eq!(meta.title(), None);
+    }
+
+    #[test]
+    fn strips_icy_metadata() {
+        let meta = StreamMeta::default();
+        let url = serve(body(), Some(1000));
+        let mut stream = HttpStream::open(url.as_str(), meta.clone()).unwrap();
+        let mut read = vec![];
+        stream.read_to_end(&mut read).unwrap();
+        assert_eq!(read, body());
+        assert_eq!(meta.title().as_deref(), Some(TITLE));
+        // every block repeats the title, it starts once
+        assert_eq!(meta.log().len(), 1);
+    }
+
+    #[test]
+    fn demuxes_byte_by_byte() {
+        let meta = StreamMeta::default();
+        let mut demux = IcyDemux::new(1000);
+        let audio: Vec<u8> = interleave(&body(), 1000)
+            .iter()
+            .flat_map(|byte| demux.feed(&[*byte], &meta))
+            .collect();
+        assert_eq!(audio, body());
+        assert_eq!(meta.title().as_deref(), Some(TITLE));
+    }
+
+    #[test]
+    fn reads_utf8_and_latin1_titles() {
+        let utf8 = "StreamTitle='Café - À bientôt';StreamUrl='';\0\0";
+        assert_eq!(stream_title(utf8.as_bytes()).unwrap(), "Café - À bientôt");
+        let latin1 = b"StreamTitle='Caf\xe9 - \xc0 bient\xf4t';StreamUrl='';\0\0";
+        assert_eq!(stream_title(latin1).unwrap(), "Café - À bientôt");
+        assert_eq!(stream_title(b"StreamTitle='';\0\0\0"), None);
+    }
+}
@@ -0,0 +1,321 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    collections::VecDeque,
+    f32::consts::PI,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use rodio::Source;
+
+pub const MIN_SPEED: f32 = 0.5;
+pub const MAX_SPEED: f32 = 2.0;
+
+// 20ms hop, 40ms segments
+const HOP_MS: u32 = 20;
+// how far a segment may move to line up with the previous one
+const TOLERANCE_MS: u32 = 8;
+
+/// Playback speed shared with every [`TimeStretch`] in the chain.
+#[derive(Clone)]
+pub struct SpeedControl(Arc<AtomicU32>);
+
+impl SpeedControl {
+    pub fn new(speed: f32) -> Self {
+        Self(Arc::new(AtomicU32::new(speed.to_bits())))
+    }
+
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    pub fn set(&self, speed: f32) {
+        let speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+        self.0.store(speed.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// WSOLA time stretcher: changes tempo while keeping the pitch.
+///
+/// Segments of `2 * hop` frames are taken from the input every `hop * speed`
+/// frames, nudged by up to `tolerance` frames to best match the natural
+/// continuation of the previous segment, then Hann windowed and overlap-added
+/// every `hop` frames. At 1.0x the natural continuation is always chosen,
+/// which reconstructs the input exactly.
+pub struct TimeStretch<I>
+where
+    I: Source<Item = f32>,
+{
+    input: I,
+    speed: SpeedControl,
+    channels: usize,
+    sample_rate: u32,
+    hop: usize,
+    tolerance: usize,
+    window: Vec<f32>,
+    // interleaved input, buf[0] is the first sample of frame `buf_start`
+    buf: VecDeque<f32>,
+    buf_start: usize,
+    exhausted: bool,
+    // ideal position of the next segment
+    nominal: f64,
+    // start frame of the last segment
+    prev: Option<usize>,
+    // windowed second half of the last segment
+    tail: Vec<f32>,
+    out: VecDeque<f32>,
+    finished: bool,
+}
+
+impl<I> TimeStretch<I>
+where
+    I: Source<Item = f32>,
+{
+    pub fn new(input: I, speed: SpeedControl) -> Self {
+        let channels = input.channels().max(1) as usize;
+        let sample_rate = input.sample_rate();
+        let hop = (sample_rate * HOP_MS / 1000).max(1) as usize;
+        let tolerance = (sample_rate * TOLERANCE_MS / 1000) as usize;
+        let len = hop * 2;
+        // periodic hann, two halves overlapped sum to 1
+        let window = (0..len)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / len as f32).cos())
+            .collect();
+        Self {
+            input,
+            speed,
+            channels,
+            sample_rate,
+            hop,
+            tolerance,
+            window,
+            buf: VecDeque::new(),
+            buf_start: 0,
+            exhausted: false,
+            nominal: 0.0,
+            prev: None,
+            tail: vec![0.0; hop * channels],
+            out: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    // read input until frame `end` is buffered
+    fn fill(&mut self, end: usize) {
+        while !self.exhausted && self.buf_start + self.buf.len() / self.channels < end {
+            for _ in 0..self.channels {
+                match self.input.next() {
+                    Some(sample) => self.buf.push_back(sample),
+                    None => {
+                        self.exhausted = true;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    fn sample(&self, frame: usize, channel: usize) -> f32 {
+        if frame < self.buf_start {
+            return 0.0;
+        }
+        let index = (frame - self.buf_start) * self.channels + channel;
+        self.buf.get(index).copied().unwrap_or(0.0)
+    }
+
+    fn mono(&self, frame: usize) -> f32 {
+        (0..self.channels).map(|c| self.sample(frame, c)).sum()
+    }
+
+    fn correlation(&self, a: usize, b: usize) -> f32 {
+        // every other frame is plenty for alignment
+        (0..self.hop)
+            .step_by(2)
+            .map(|i| self.mono(a + i) * self.mono(b + i))
+            .sum()
+    }
+
+    // best segment start around `nominal` to follow `natural`
+    fn search(&mut self, natural: usize) -> usize {
+        let center = self.nominal.round() as usize;
+        let low = center.saturating_sub(self.tolerance).max(self.buf_start);
+        let high = center + self.tolerance;
+        self.fill(high.max(natural) + self.hop);
+        let mut best = center.max(low);
+        let mut best_score = f32::MIN;
+        for candidate in (low..=high).step_by(2) {
+            let score = self.correlation(candidate, natural);
+            if score > best_score {
+                best_score = score;
+                best = candidate;
+            }
+        }
+        // refine around the coarse pick
+        for candidate in [best.saturating_sub(1).max(low), best + 1] {
+            let score = self.correlation(candidate, natural);
+            if score > best_score {
+                best_score = score;
+                best = candidate;
+            }
+        }
+        best
+    }
+
+    // place one more segment, filling `out` with `hop` frames
+    fn step(&mut self) {
+        let speed = self.speed.get();
+        let hop = self.hop;
+        let start = match self.prev {
+            None => self.nominal as usize,
+            Some(prev) => {
+                let natural = prev + hop;
+                if (speed - 1.0).abs() < f32::EPSILON {
+                    self.nominal = natural as f64;
+                    natural
+                } else {
+                    self.nominal += hop as f64 * speed as f64;
+                    self.search(natural)
+                }
+            }
+        };
+        self.fill(start + hop * 2);
+        let end = self.buf_start + self.buf.len() / self.channels;
+        if self.exhausted && start >= end {
+            // flush what is left of the last segment, without the padding
+            let left = match self.prev {
+                Some(prev) => end.saturating_sub(prev + hop).min(hop),
+                None => 0,
+            };
+            self.out.extend(self.tail.drain(..left * self.channels));
+            self.finished = true;
+            return;
+        }
+        // the input may end inside this segment, nothing is played past its last frame
+        let len = match self.exhausted {
+            true => hop.min(end - start),
+            false => hop,
+        };
+        for i in 0..len {
+            for c in 0..self.channels {
+                let x = self.sample(start + i, c);
+                let sample = match self.prev {
+                    // nothing to overlap with, keep the onset intact
+                    None => x,
+                    Some(_) => self.tail[i * self.channels + c] + self.window[i] * x,
+                };
+                self.out.push_back(sample);
+            }
+        }
+        if len < hop {
+            self.finished = true;
+            return;
+        }
+        for i in 0..hop {
+            for c in 0..self.channels {
+                self.tail[i * self.channels + c] =
+                    self.window[hop + i] * self.sample(start + hop + i, c);
+            }
+        }
+        self.prev = Some(start);
+        // drop input that no later segment can reach
+        let keep = (start + hop).min((self.nominal as usize).saturating_sub(self.tolerance));
+        if keep > self.buf_start {
+            let drop = ((keep - self.buf_start) * self.channels).min(self.buf.len());
+            self.buf.drain(..drop);
+            self.buf_start += drop / self.channels;
+        }
+    }
+}
+
+impl<I> Iterator for TimeStretch<I>
+where
+    I: Source<Item = f32>,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        while self.out.is_empty() && !self.finished {
+            self.step();
+        }
+        self.out.pop_front()
+    }
+}
+
+impl<I> Source for TimeStretch<I>
+where
+    I: Source<Item = f32>,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        self.channels as u16
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rodio::buffer::SamplesBuffer;
+
+    use super::{SpeedControl, TimeStretch};
+
+    // a ramp, so every sample is distinct
+    fn input(frames: usize) -> Vec<f32> {
+        (0..frames * 2)
+            .map(|i| (i % 1000) as f32 / 1000.0)
+            .collect()
+    }
+
+    #[test]
+    fn ends_with_the_input() {
+        // a whole number of hops and one that ends inside the last hop
+        for frames in [8000, 8123] {
+            let samples = input(frames);
+            let source = SamplesBuffer::new(2, 8000, samples.clone());
+            let out: Vec<f32> = TimeStretch::new(source, SpeedControl::new(1.0)).collect();
+            assert_eq!(out.len(), samples.len());
+            for (a, b) in out.iter().zip(&samples) {
+                assert!((a - b).abs() < 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn no_padding_when_sped_up() {
+        let frames = 16123;
+        let source = SamplesBuffer::new(2, 8000, input(frames));
+        let out = TimeStretch::new(source, SpeedControl::new(2.0)).count() / 2;
+        // about half as long, never more than the input allows
+        let hop = 160;
+        assert!(out <= frames / 2 + hop, "{} frames", out);
+        assert!(out >= frames / 2 - hop, "{} frames", out);
+    }
+}
@@ -0,0 +1,277 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use rodio::{Sample, Source};
+
+const NS_PER_SECOND: u128 = 1_000_000_000;
+
+/// Playback position shared between the output thread and the player.
+///
+/// The position is derived from the samples actually pulled out of the
+/// tracked source, so it stands still while the sink is paused or starved.
+#[derive(Clone, Default)]
+pub struct Position {
+    inner: Arc<PositionInner>,
+}
+
+#[derive(Default)]
+struct PositionInner {
+    nanos: AtomicU64,
+    started: AtomicBool,
+    done: AtomicBool,
+}
+
+impl Position {
+    pub fn new(start: Duration) -> Self {
+        let position = Self::default();
+        position
+            .inner
+            .nanos
+            .store(start.as_nanos() as u64, Ordering::Relaxed);
+        position
+    }
+
+    /// position inside the track
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.inner.nanos.load(Ordering::Relaxed))
+    }
+
+    /// whether the first sample has been delivered
+    pub fn started(&self) -> bool {
+        self.inner.started.load(Ordering::Relaxed)
+    }
+
+    /// whether the last sample has left the chain, see [`Done`]
+    pub fn is_done(&self) -> bool {
+        self.inner.done.load(Ordering::Relaxed)
+    }
+}
+
+/// Source wrapper which publishes its position to a [`Position`].
+pub struct Tracked<I> {
+    input: I,
+    position: Position,
+    // nanos accumulated before the current channels/sample rate
+    base: u128,
+    samples: u64,
+    channels: u16,
+    sample_rate: u32,
+}
+
+impl<I> Tracked<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    pub fn new(input: I, position: Position) -> Self {
+        let base = position.elapsed().as_nanos();
+        let channels = input.channels();
+        let sample_rate = input.sample_rate();
+        Self {
+            input,
+            position,
+            base,
+            samples: 0,
+            channels,
+            sample_rate,
+        }
+    }
+}
+
+impl<I> Iterator for Tracked<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        // the spec reported before `next` belongs to the upcoming sample
+        let channels = self.input.channels();
+        let sample_rate = self.input.sample_rate();
+        if channels != self.channels || sample_rate != self.sample_rate {
+            self.base += self.samples_nanos();
+            self.samples = 0;
+            self.channels = channels;
+            self.sample_rate = sample_rate;
+        }
+        let sample = self.input.next();
+        if sample.is_some() {
+            let inner = &self.position.inner;
+            if self.samples == 0 {
+                inner.started.store(true, Ordering::Relaxed);
+            }
+            self.samples += 1;
+            let nanos = self.base + self.samples_nanos();
+            inner.nanos.store(nanos as u64, Ordering::Relaxed);
+        }
+        sample
+    }
+}
+
+impl<I> Tracked<I> {
+    fn samples_nanos(&self) -> u128 {
+        let per_second = self.sample_rate as u128 * self.channels as u128;
+        if per_second == 0 {
+            return 0;
+        }
+        self.samples as u128 * NS_PER_SECOND / per_second
+    }
+}
+
+impl<I> Source for Tracked<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
+
+/// Source wrapper which marks a [`Position`] done once it is exhausted. It goes
+/// last in the chain, stages that read ahead of [`Tracked`] have played out by then.
+pub struct Done<I> {
+    input: I,
+    position: Position,
+}
+
+impl<I> Done<I> {
+    pub fn new(input: I, position: Position) -> Self {
+        Self { input, position }
+    }
+}
+
+impl<I> Iterator for Done<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        let sample = self.input.next();
+        if sample.is_none() {
+            self.position.inner.done.store(true, Ordering::Relaxed);
+        }
+        sample
+    }
+}
+
+impl<I> Source for Done<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
+
+/// Drops a pass still waiting in the sink, through the [`Cancellable`] sources of it.
+#[derive(Clone, Default)]
+pub struct Cancel {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Cancel {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Source wrapper which ends at once when its [`Cancel`] is used, the sink moves on
+/// to whatever was appended after it.
+pub struct Cancellable<I> {
+    input: I,
+    cancel: Cancel,
+}
+
+impl<I> Cancellable<I> {
+    pub fn new(input: I, cancel: Cancel) -> Self {
+        Self { input, cancel }
+    }
+}
+
+impl<I> Iterator for Cancellable<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        if self.cancel.cancelled.load(Ordering::Relaxed) {
+            return None;
+        }
+        self.input.next()
+    }
+}
+
+impl<I> Source for Cancellable<I>
+where
+    I: Source,
+    I::Item: Sample,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        self.input.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+}
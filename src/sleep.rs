@@ -0,0 +1,92 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::time::{Duration, Instant};
+
+use crate::media::player::{MusicPlayer, Player, PlayerError};
+
+// each press of the timer key adds this much
+pub const SLEEP_STEP: Duration = Duration::from_secs(15 * 60);
+// the last seconds before sleep fade out
+const FADE_OUT: Duration = Duration::from_secs(10);
+
+pub enum SleepTimer {
+    Off,
+    // pause at the deadline
+    At(Instant),
+    // pause once the current queue item has finished
+    AfterCurrent,
+}
+
+impl SleepTimer {
+    /// start the timer or give it another `SLEEP_STEP`
+    pub fn extend(&mut self, player: &mut MusicPlayer) -> Result<(), PlayerError> {
+        player.restore_fade();
+        *self = match self {
+            SleepTimer::At(deadline) => SleepTimer::At(*deadline + SLEEP_STEP),
+            _ => SleepTimer::At(Instant::now() + SLEEP_STEP),
+        };
+        player.set_stop_after_current(false)
+    }
+
+    pub fn after_current(&mut self, player: &mut MusicPlayer) -> Result<(), PlayerError> {
+        player.restore_fade();
+        *self = SleepTimer::AfterCurrent;
+        player.set_stop_after_current(true)
+    }
+
+    pub fn cancel(&mut self, player: &mut MusicPlayer) -> Result<(), PlayerError> {
+        player.restore_fade();
+        *self = SleepTimer::Off;
+        player.set_stop_after_current(false)
+    }
+
+    /// header text, None while the timer is off
+    pub fn countdown(&self) -> Option<String> {
+        match self {
+            SleepTimer::Off => None,
+            SleepTimer::At(deadline) => {
+                let left = deadline.saturating_duration_since(Instant::now()).as_secs();
+                Some(format!("sleep in {:0>2}:{:0>2}", left / 60, left % 60))
+            }
+            SleepTimer::AfterCurrent => Some("sleep after this one".to_string()),
+        }
+    }
+
+    /// called on every player tick
+    pub fn tick(&mut self, player: &mut MusicPlayer) {
+        match self {
+            SleepTimer::Off => {}
+            SleepTimer::At(deadline) => {
+                let left = deadline.saturating_duration_since(Instant::now());
+                if left.is_zero() {
+                    // resuming ramps back up
+                    let _ = player.pause();
+                    *self = SleepTimer::Off;
+                } else if left <= FADE_OUT && !player.is_fading_out() {
+                    player.fade_out(left);
+                }
+            }
+            SleepTimer::AfterCurrent => {
+                // the player clears the flag when it stops
+                if !player.stop_after_current {
+                    *self = SleepTimer::Off;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,60 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use tui::{
+    backend::Backend,
+    layout::{Alignment, Rect},
+    widgets::{Block, BorderType, Borders, Paragraph},
+    Frame,
+};
+
+use crate::app::App;
+
+pub fn draw_channels<B>(app: &mut App, frame: &mut Frame<B>, area: Rect)
+where
+    B: Backend,
+{
+    let channels = &app.player.channels;
+    // L 30 / R 0 style, centred is C
+    let balance = channels.balance();
+    let mut s = if balance.abs() < 0.05 {
+        String::from("C")
+    } else if balance < 0.0 {
+        format!("L{:.0}", -balance * 100.0)
+    } else {
+        format!("R{:.0}", balance * 100.0)
+    };
+    for (on, flag) in [
+        (channels.is_mono(), "mono"),
+        (channels.is_swapped(), "swap"),
+        (channels.is_crossfeed(), "xfeed"),
+    ] {
+        if on {
+            s.push(' ');
+            s.push_str(flag);
+        }
+    }
+    let text = Paragraph::new(s)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .title("Channels")
+                .title_alignment(Alignment::Center),
+        );
+    frame.render_widget(text, area);
+}
@@ -0,0 +1,71 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use tui::{
+    backend::Backend,
+    layout::{Alignment, Rect},
+    style::{Color, Modifier, Style},
+    widgets::{Block, BorderType, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+
+use crate::{app::App, media::device::OutputDevice};
+
+use super::popup_area;
+
+pub struct DevicePicker {
+    // None is the system default device
+    pub devices: Vec<Option<OutputDevice>>,
+    pub state: ListState,
+}
+
+pub fn draw_device_picker<B>(app: &mut App, frame: &mut Frame<B>, area: Rect)
+where
+    B: Backend,
+{
+    let current = app.player.device.clone();
+    let picker = match &mut app.device_picker {
+        Some(picker) => picker,
+        None => return,
+    };
+    let mut items = vec![];
+    for device in &picker.devices {
+        let name = match device {
+            Some(device) => format!("{}: {}", device.host, device.name),
+            None => String::from("System default"),
+        };
+        let mut item = ListItem::new(name);
+        if *device == current {
+            item = item.style(Style::default().add_modifier(Modifier::BOLD));
+        }
+        items.push(item);
+    }
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title("Output Device (Enter: select, Esc: close)")
+                .title_alignment(Alignment::Center)
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(Color::Cyan)),
+        )
+        .highlight_style(Style::default().bg(Color::Cyan))
+        .highlight_symbol("> ");
+    let area = popup_area(60, 50, area);
+    frame.render_widget(Clear, area);
+    frame.render_stateful_widget(list, area, &mut picker.state);
+}
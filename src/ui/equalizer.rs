@@ -0,0 +1,108 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use tui::{
+    backend::Backend,
+    layout::{Alignment, Rect},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, BorderType, Borders, Clear, Paragraph},
+    Frame,
+};
+
+use crate::{
+    app::App,
+    media::equalizer::{FREQUENCIES, MAX_GAIN},
+};
+
+use super::popup_area;
+
+pub struct EqView {
+    // band the arrow keys change
+    pub band: usize,
+}
+
+// one cell per dB either side of the centre line
+fn gain_bar(gain: f32) -> String {
+    let half = MAX_GAIN as i32;
+    let cells = gain.round() as i32;
+    let mut bar = String::new();
+    for i in -half..=half {
+        let ch = if i == 0 {
+            '┼'
+        } else if (cells < 0 && i >= cells && i < 0) || (cells > 0 && i <= cells && i > 0) {
+            '█'
+        } else {
+            '·'
+        };
+        bar.push(ch);
+    }
+    bar
+}
+
+pub fn draw_equalizer<B>(app: &mut App, frame: &mut Frame<B>, area: Rect)
+where
+    B: Backend,
+{
+    let view = match &app.eq_view {
+        Some(view) => view,
+        None => return,
+    };
+    let eq = &app.player.equalizer;
+    let gains = eq.gains();
+    let mut lines = vec![];
+    for (i, (freq, gain)) in FREQUENCIES.iter().zip(gains).enumerate() {
+        let freq = match *freq >= 1000.0 {
+            true => format!("{}k", freq / 1000.0),
+            false => format!("{}", freq),
+        };
+        let line = format!("{:>4} Hz {:>+5.1} dB  {}", freq, gain, gain_bar(gain));
+        let mut style = Style::default();
+        if i == view.band {
+            style = style.bg(Color::Cyan).add_modifier(Modifier::BOLD);
+        }
+        if !eq.is_enabled() {
+            style = style.fg(Color::DarkGray);
+        }
+        lines.push(Spans::from(Span::styled(line, style)));
+    }
+    lines.push(Spans::from(""));
+    lines.push(Spans::from(
+        "←/→ gain  ↑/↓ band  0 flat  p/P preset  t on/off  w save preset  Esc close",
+    ));
+    let preset = app
+        .config
+        .settings
+        .eq_preset
+        .clone()
+        .unwrap_or_else(|| "Custom".to_string());
+    let state = match eq.is_enabled() {
+        true => "on",
+        false => "off",
+    };
+    let paragraph = Paragraph::new(lines).alignment(Alignment::Center).block(
+        Block::default()
+            .title(format!("Equalizer: {} ({})", preset, state))
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(Color::Cyan)),
+    );
+    let area = popup_area(70, 50, area);
+    frame.render_widget(Clear, area);
+    frame.render_widget(paragraph, area);
+}
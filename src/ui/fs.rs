@@ -16,7 +16,7 @@
 // along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::env::current_dir;
-use std::fmt::Debug;
+use std::fmt::{self, Debug, Display};
 use std::fs;
 use std::fs::DirEntry;
 use std::path::Path;
@@ -31,7 +31,11 @@ use tui::Frame;
 
 use alphanumeric_sort;
 
-use crate::App;
+use crate::{
+    app::Focus,
+    media::{decoder, playlist},
+    App,
+};
 
 #[allow(dead_code)]
 pub struct FsExplorer {
@@ -43,12 +47,20 @@ pub struct FsExplorer {
     accept_suffix: Vec<&'static str>,
 }
 
-#[derive(Fail, Debug)]
-#[fail(display = "FsError: {}", msg)]
+#[derive(Debug)]
 pub struct FsError {
     msg: &'static str,
 }
 
+// by hand, the Fail derive puts its impls where newer compilers warn about them
+impl Display for FsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "FsError: {}", self.msg)
+    }
+}
+
+impl Fail for FsError {}
+
 impl FsExplorer {
     pub fn default(callback: Option<fn(Error)>) -> Result<Self, Error> {
         let path = current_dir()?;
@@ -63,7 +75,7 @@ impl FsExplorer {
             dirs: vec![],
             index: list_state,
             on_error_msg_callback: callback,
-            accept_suffix: vec!["mp3", "wav", "flac"],
+            accept_suffix: [decoder::extensions(), playlist::EXTENSIONS.to_vec()].concat(),
         };
         let (dirs, files) = exp.visit_dir(path_str)?;
         exp.files = files;
@@ -73,12 +85,9 @@ impl FsExplorer {
 
     pub fn refresh(&mut self) {
         let str = String::from(self.current_path.as_str());
-        match self.visit_dir(str.as_str()) {
-            Ok(entries) => {
-                self.dirs = entries.0;
-                self.files = entries.1;
-            }
-            Err(_) => {}
+        if let Ok(entries) = self.visit_dir(str.as_str()) {
+            self.dirs = entries.0;
+            self.files = entries.1;
         }
     }
 
@@ -118,10 +127,10 @@ impl FsExplorer {
             }
         }
         dir_entries.sort_by(|item1, item2| {
-            return alphanumeric_sort::compare_os_str(item1.file_name(), item2.file_name());
+            alphanumeric_sort::compare_os_str(item1.file_name(), item2.file_name())
         });
         file_entries.sort_by(|item1, item2| {
-            return alphanumeric_sort::compare_os_str(item1.file_name(), item2.file_name());
+            alphanumeric_sort::compare_os_str(item1.file_name(), item2.file_name())
         });
         
         Ok((dir_entries, file_entries))
@@ -173,11 +182,13 @@ where
         .borders(Borders::ALL)
         .border_type(BorderType::Rounded);
     
-    blk = blk.border_style(Style::default().fg(Color::Cyan));
-
-    let file_list = List::new(items)
-        .block(blk)
-        .highlight_style(Style::default().bg(Color::Cyan))
-        .highlight_symbol("> ");
+    let mut file_list = List::new(items);
+    if app.focus == Focus::Explorer {
+        blk = blk.border_style(Style::default().fg(Color::Cyan));
+        file_list = file_list
+            .highlight_style(Style::default().bg(Color::Cyan))
+            .highlight_symbol("> ");
+    }
+    let file_list = file_list.block(blk);
     frame.render_stateful_widget(file_list, fs_chunks[1], &mut fse.index);
 }
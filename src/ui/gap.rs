@@ -5,21 +5,62 @@ use tui::{
     Frame,
 };
 
-use crate::app::App;
+use crate::{
+    app::App,
+    media::player::{GapMode, Transition},
+};
+
+use super::play_list::selected_item;
 
 pub fn draw_gap<B>(app: &mut App, frame: &mut Frame<B>, area: Rect)
 where
     B: Backend,
 {
     let player = &app.player;
-    let gap = player.gap;
-    let s = format!("(j)◄ {:.1} s ►(k)", gap);
+    let selected = selected_item(app);
+    // the song the values are shown for
+    let (offset, effective) = match selected {
+        Some(item) => (
+            item.gap,
+            player.gap_for(item, item.duration.unwrap_or_default()),
+        ),
+        None => match (player.gap_mode, player.play_list.playing()) {
+            // J/K move the offset of the current song
+            (GapMode::Shadowing, Some(item)) => (item.gap, player.gap_after()),
+            _ => (player.gap, player.gap_after()),
+        },
+    };
+    let (title, s) = match (player.transition, player.gap_mode, selected) {
+        (Transition::Crossfade, _, _) => (
+            "Crossfade (f)",
+            format!("(j)◄ {:.1} s ►(k)", player.crossfade),
+        ),
+        (Transition::Gap, GapMode::Fixed, selected) => (
+            if selected.is_some() {
+                "Gap (song)"
+            } else {
+                "Gap (f)"
+            },
+            format!("(j)◄ {:.1} s ►(k)", offset),
+        ),
+        (Transition::Gap, GapMode::Shadowing, selected) => (
+            if selected.is_some() {
+                "Shadowing (song)"
+            } else {
+                "Shadowing (f)"
+            },
+            format!(
+                "(j)◄ {:.2}x+{:.1}s ►(k) = {:.1} s",
+                player.gap_factor, offset, effective
+            ),
+        ),
+    };
     let text = Paragraph::new(s)
         .block(
             Block::default()
                 .borders(Borders::ALL)
                 .border_type(BorderType::Rounded)
-                .title("Gap")
+                .title(title)
                 .title_alignment(Alignment::Center),
         );
     frame.render_widget(text, area);
@@ -0,0 +1,65 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use tui::{
+    backend::Backend,
+    layout::{Alignment, Rect},
+    style::{Color, Style},
+    widgets::{Block, BorderType, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+
+use crate::app::App;
+
+use super::popup_area;
+
+pub struct HistoryView {
+    // newest entry first
+    pub state: ListState,
+}
+
+pub fn draw_history<B>(app: &mut App, frame: &mut Frame<B>, area: Rect)
+where
+    B: Backend,
+{
+    let view = match &mut app.history_view {
+        Some(view) => view,
+        None => return,
+    };
+    let mut items = vec![];
+    for entry in app.player.history.iter().rev() {
+        let line = format!("{}  {}", entry.played_at.format("%H:%M:%S"), entry.name);
+        items.push(ListItem::new(line));
+    }
+    if items.is_empty() {
+        items.push(ListItem::new("Nothing played yet"));
+    }
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title("History (Esc: close)")
+                .title_alignment(Alignment::Center)
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(Color::Cyan)),
+        )
+        .highlight_style(Style::default().bg(Color::Cyan))
+        .highlight_symbol("> ");
+    let area = popup_area(60, 50, area);
+    frame.render_widget(Clear, area);
+    frame.render_stateful_widget(list, area, &mut view.state);
+}
@@ -15,13 +15,42 @@
 // You should have received a copy of the GNU General Public License
 // along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
 
+use tui::layout::{Constraint, Direction, Layout, Rect};
+
 pub mod repetition;
 pub mod fs;
 pub mod play_list;
 pub mod progress;
 pub mod music_board;
 pub mod gap;
+pub mod speed;
+pub mod device;
+pub mod queue_mode;
+pub mod history;
+pub mod equalizer;
+pub mod channels;
+pub mod station_log;
 
 pub enum EventType {
     Player,
 }
+
+// centered rect taking the given percentage of `area`
+pub fn popup_area(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
+}
@@ -26,9 +26,13 @@ use tui::{
 
 use crate::{app::App, media::player::Player};
 
-use super::{play_list::draw_play_list, progress::draw_progress, repetition::draw_repeat, gap::draw_gap};
+use super::{
+    channels::draw_channels, gap::draw_gap, play_list::draw_play_list, progress::draw_progress,
+    queue_mode::draw_queue_mode, repetition::draw_repeat, speed::draw_speed,
+};
 
 pub struct MusicController {
+    // cursor of the playlist pane
     pub state: ListState,
 }
 
@@ -63,9 +67,31 @@ where
         Row::new(["Enter","select/play all",]),
         Row::new(["Space", "pause/resume"]),
         Row::new(["Esc", "parent folder"]),
-        Row::new(["n", "next"]),
+        Row::new(["n/N", "next/previous"]),
+        Row::new([",/.", "seek -/+ 5s"]),
+        Row::new(["</>", "seek -/+ 30s"]),
+        Row::new(["0-9", "jump to 0%-90%"]),
+        Row::new(["a/b", "set loop start/end"]),
+        Row::new(["x", "clear loop"]),
+        Row::new(["J/K", "shadowing gap offset"]),
         Row::new(["q", "quit"]),
         Row::new(["c", "clear list"]),
+        Row::new(["r", "repeat off/one/all"]),
+        Row::new(["s", "shuffle"]),
+        Row::new(["d", "output device"]),
+        Row::new(["l", "play history"]),
+        Row::new(["y", "titles played on the station"]),
+        Row::new(["e", "equalizer"]),
+        Row::new(["(/)", "balance left/right"]),
+        Row::new(["m/w/o", "mono/swap L-R/crossfeed"]),
+        Row::new(["t/T/z", "sleep +15 min/after this one/cancel"]),
+        Row::new(["p", "play next"]),
+        Row::new(["v", "add split into sentences"]),
+        Row::new(["F2", "save queue as .m3u8"]),
+        Row::new(["Tab", "focus explorer/playlist"]),
+        Row::new(["Enter", "playlist: jump to song"]),
+        Row::new(["Del", "playlist: remove song"]),
+        Row::new(["[/]", "playlist: move song up/down"]),
         Row::new(["↑/↓", "change selected index"]),
     ])
     .header(
@@ -87,10 +113,19 @@ where
 
     let bottom_layout_chunks = Layout::default()
         .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .constraints([
+            Constraint::Percentage(20),
+            Constraint::Percentage(20),
+            Constraint::Percentage(20),
+            Constraint::Percentage(20),
+            Constraint::Percentage(20),
+        ])
         .split(main_layout_chunks[1]);
     draw_repeat(app, frame, bottom_layout_chunks[0]);
     draw_gap(app, frame, bottom_layout_chunks[1]);
+    draw_speed(app, frame, bottom_layout_chunks[2]);
+    draw_queue_mode(app, frame, bottom_layout_chunks[3]);
+    draw_channels(app, frame, bottom_layout_chunks[4]);
 
     draw_progress(app, frame, main_layout_chunks[2]);
 }
@@ -106,7 +141,16 @@ where
         .split(area);
 
     let playing_text = if let Some(item) = player.playing_song() {
-        String::from(item.name.as_str())
+        match &item.meta {
+            // what the station is playing, then the station
+            Some(meta) => match (meta.title(), meta.station()) {
+                (Some(title), Some(station)) => format!("{} ({})", title, station),
+                (Some(title), None) => title,
+                (None, Some(station)) => station,
+                (None, None) => item.name.clone(),
+            },
+            None => String::from(item.name.as_str()),
+        }
     } else {
         String::from("None")
     };
@@ -18,11 +18,24 @@
 use tui::{
     backend::Backend,
     layout::{Alignment, Rect},
+    style::{Color, Modifier, Style},
     widgets::{Block, BorderType, Borders, List, ListItem},
     Frame,
 };
 
-use crate::app::App;
+use crate::{
+    app::{App, Focus},
+    media::player::PlayListItem,
+};
+
+// the song under the cursor while the playlist has focus, its settings are edited then
+pub fn selected_item(app: &App) -> Option<&PlayListItem> {
+    if app.focus != Focus::Playlist {
+        return None;
+    }
+    let selected = app.music_controller.state.selected()?;
+    app.player.play_list.lists.get(selected)
+}
 
 pub fn draw_play_list<B>(app: &mut App, frame: &mut Frame<B>, area: Rect)
 where
@@ -30,15 +43,42 @@ where
 {
     let mut items = vec![];
     let player = &app.player;
-    for item in &player.play_list.lists {
-        items.push(ListItem::new(item.name.as_str()))
+    for (i, item) in player.play_list.lists.iter().enumerate() {
+        let mut name = item.name.clone();
+        if item.repetition != 1 || item.gap > 0.0 {
+            name = format!("{} x{} {:.1}s", name, item.repetition, item.gap);
+        }
+        if player.play_list.current == Some(i) {
+            let name = "▶ ".to_owned() + name.as_str();
+            items.push(
+                ListItem::new(name).style(
+                    Style::default()
+                        .fg(Color::LightCyan)
+                        .add_modifier(Modifier::BOLD),
+                ),
+            );
+        } else {
+            items.push(ListItem::new("  ".to_owned() + name.as_str()));
+        }
+    }
+    let mut blk = Block::default()
+        .borders(Borders::ALL)
+        .title("Playlist (Tab)")
+        .border_type(BorderType::Rounded)
+        .title_alignment(Alignment::Center);
+    let mut list = List::new(items);
+    if app.focus == Focus::Playlist {
+        blk = blk.border_style(Style::default().fg(Color::Cyan));
+        list = list
+            .highlight_style(Style::default().bg(Color::Cyan))
+            .highlight_symbol("> ");
+    }
+    let state = &mut app.music_controller.state;
+    if state
+        .selected()
+        .is_some_and(|selected| selected >= player.play_list.lists.len())
+    {
+        state.select(player.play_list.lists.len().checked_sub(1));
     }
-    let list = List::new(items).block(
-        Block::default()
-            .borders(Borders::ALL)
-            .title("Playlist")
-            .border_type(BorderType::Rounded)
-            .title_alignment(Alignment::Center),
-    );
-    frame.render_widget(list, area);
+    frame.render_stateful_widget(list.block(blk), area, state);
 }
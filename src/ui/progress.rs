@@ -15,12 +15,14 @@
 // You should have received a copy of the GNU General Public License
 // along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
 
+use std::time::Duration;
+
 use tui::{
     backend::Backend,
     layout::{Constraint, Layout, Rect},
     style::{Color, Modifier, Style},
     symbols::{self},
-    widgets::LineGauge,
+    widgets::{LineGauge, Paragraph},
     Frame,
 };
 
@@ -32,8 +34,9 @@ where
 {
     let player = &app.player;
 
-    let current_time = player.current_time;
-    let total_time = player.total_time;
+    let (current, total) = player.get_progress();
+    let current_time = Duration::from_secs_f32(current);
+    let total_time = Duration::from_secs_f32(total);
 
     let minute_mins = current_time.as_secs() / 60;
     let minute_secs = current_time.as_secs() % 60;
@@ -43,19 +46,40 @@ where
     let mut percent = 0.0;
     if total_time.as_secs() != 0 {
         percent = if player.is_playing() || player.is_paused() {
-            current_time.as_secs_f64() / total_time.as_secs_f64()
+            // the estimated length can be a little short
+            (current_time.as_secs_f64() / total_time.as_secs_f64()).min(1.0)
         } else {
             0.0
         };
     }
-    let s = if player.is_playing() || player.is_paused() {
-        format!(
-            "{:0>2}:{:0>2} / {:0>2}:{:0>2}",
-            minute_mins, minute_secs, total_mins, total_secs
-        )
+    let mut s = if player.is_playing() || player.is_paused() {
+        // streams have no length
+        let total = match total_time.is_zero() {
+            true => "--:--".to_string(),
+            false => format!("{:0>2}:{:0>2}", total_mins, total_secs),
+        };
+        format!("{:0>2}:{:0>2} / {}", minute_mins, minute_secs, total)
     } else {
         "Not Playing".to_string()
     };
+    if (player.speed() - 1.0).abs() > f32::EPSILON && player.is_playing() {
+        let left = player.remaining_time.as_secs();
+        s = format!(
+            "{} (-{:0>2}:{:0>2} at {:.1}x)",
+            s,
+            left / 60,
+            left % 60,
+            player.speed()
+        );
+    }
+    if let Some(ab) = &player.ab_loop {
+        if ab.b.is_some() {
+            s = format!("{} [A-B x{}]", s, ab.remaining);
+        } else {
+            s = format!("{} [A-]", s);
+        }
+    }
+    let label_len = s.len() as u16;
 
     let gauge = LineGauge::default()
         .ratio(percent)
@@ -73,4 +97,32 @@ where
         .constraints([Constraint::Percentage(100)].as_ref())
         .split(area);
     frame.render_widget(gauge, layout[0]);
+
+    // loop markers on top of the gauge line
+    if let Some(ab) = &player.ab_loop {
+        if total_time.is_zero() {
+            return;
+        }
+        let area = layout[0];
+        // LineGauge draws the line one column after its label
+        let start = area.left() + label_len + 1;
+        if start >= area.right() {
+            return;
+        }
+        let width = area.right() - start;
+        let mut marks = vec![(ab.a, "A")];
+        if let Some(b) = ab.b {
+            marks.push((b, "B"));
+        }
+        for (pos, mark) in marks {
+            let ratio = (pos.as_secs_f64() / total_time.as_secs_f64()).min(1.0);
+            let x = (start + (width as f64 * ratio).floor() as u16).min(area.right() - 1);
+            let marker = Paragraph::new(mark).style(
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            );
+            frame.render_widget(marker, Rect::new(x, area.top(), 1, 1));
+        }
+    }
 }
@@ -0,0 +1,47 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use tui::{
+    backend::Backend,
+    layout::{Alignment, Rect},
+    widgets::{Block, BorderType, Borders, Paragraph},
+    Frame,
+};
+
+use crate::{app::App, media::player::QueueMode};
+
+pub fn draw_queue_mode<B>(app: &mut App, frame: &mut Frame<B>, area: Rect)
+where
+    B: Backend,
+{
+    let player = &app.player;
+    let s = match player.queue_mode {
+        QueueMode::Off => "Off",
+        QueueMode::RepeatOne => "Repeat one",
+        QueueMode::RepeatAll => "Repeat all",
+        QueueMode::Shuffle => "Shuffle",
+    };
+    let text = Paragraph::new(s)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .title("Queue (r/s)")
+                .title_alignment(Alignment::Center),
+        );
+    frame.render_widget(text, area);
+}
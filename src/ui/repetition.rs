@@ -7,19 +7,24 @@ use tui::{
 
 use crate::app::App;
 
+use super::play_list::selected_item;
+
 pub fn draw_repeat<B>(app: &mut App, frame: &mut Frame<B>, area: Rect)
 where
     B: Backend,
 {
     let player = &app.player;
-    let rep = player.repetition;
+    let (title, rep) = match selected_item(app) {
+        Some(item) => ("Repeat (song)", item.repetition),
+        None => ("Repeat", player.repetition),
+    };
     let s = format!("(g)◄ x{:} ►(h)", rep);
     let text = Paragraph::new(s)
         .block(
             Block::default()
                 .borders(Borders::ALL)
                 .border_type(BorderType::Rounded)
-                .title("Repeat")
+                .title(title)
                 .title_alignment(Alignment::Center),
         );
     frame.render_widget(text, area);
@@ -0,0 +1,75 @@
+// Copyright (C) 2022 KetaNetwork
+//
+// This file is part of RustPlayer.
+//
+// RustPlayer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// RustPlayer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with RustPlayer.  If not, see <http://www.gnu.org/licenses/>.
+
+use tui::{
+    backend::Backend,
+    layout::{Alignment, Rect},
+    style::{Color, Style},
+    widgets::{Block, BorderType, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+
+use crate::app::App;
+
+use super::popup_area;
+
+pub struct StationLogView {
+    // newest title first
+    pub state: ListState,
+}
+
+pub fn draw_station_log<B>(app: &mut App, frame: &mut Frame<B>, area: Rect)
+where
+    B: Backend,
+{
+    let view = match &mut app.station_log {
+        Some(view) => view,
+        None => return,
+    };
+    let meta = app.player.playing_song().and_then(|item| item.meta.clone());
+    let station = match &meta {
+        Some(meta) => meta.station().unwrap_or_else(|| "station".to_string()),
+        None => "station".to_string(),
+    };
+    let mut items = vec![];
+    if let Some(meta) = &meta {
+        for (at, title) in meta.log().iter().rev() {
+            items.push(ListItem::new(format!(
+                "{}  {}",
+                at.format("%H:%M:%S"),
+                title
+            )));
+        }
+    }
+    if items.is_empty() {
+        items.push(ListItem::new("No titles yet"));
+    }
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(format!("Played on {} (Esc: close)", station))
+                .title_alignment(Alignment::Center)
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .border_style(Style::default().fg(Color::Cyan)),
+        )
+        .highlight_style(Style::default().bg(Color::Cyan))
+        .highlight_symbol("> ");
+    let area = popup_area(60, 50, area);
+    frame.render_widget(Clear, area);
+    frame.render_stateful_widget(list, area, &mut view.state);
+}